@@ -1,19 +1,538 @@
-use crate::stack::{add, concat, dup, eq, mul, neq, pop, sub, swap, insert_vector, size, index, Stack, StackEl, StackElValue, LifoVector};
+use crate::stack::{add, concat, dup, eq, mul, neq, pop, sub, swap, insert_vector, size, index, dynamic_index, regex_match, capture, regex_replace, to_str, parse_int, parse_bool, explode, implode, is_digit, is_alpha, to_upper, slice, bytes_to_int, int_to_bytes, bytes_to_str, str_to_bytes, set, insert_at, remove, pop_back, sort, reverse, contains, index_of, put, get, del, has, keys, values, union, intersect, diff, to_set, to_vector, pair, unpair, car, cdr, get_field, set_field, safe_index, dynamic_safe_index, some, unwrap, if_none, left, right, if_left, type_of, compare, same_value, render_stack, Stack, StackEl, StackElValue, LifoVector, LifoMap, LifoSet, LifoRecord, Type};
 use logos::{Lexer, Logos};
+use std::collections::HashMap;
+use std::ops::Range;
+use std::rc::Rc;
 
 fn label_is_set(current_label: &Option<String>) -> bool {
     // println!("current label: {:?}", current_label);
-    match current_label {
-        None => false,
-        Some(_) => true,
+    current_label.is_some()
+}
+
+// turns the escape sequences accepted by the string token into the characters they stand for
+fn unescape_string(raw: &str) -> String {
+    let mut output = String::new();
+    let mut chars = raw.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            output.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('b') => output.push('\u{8}'),
+            Some('f') => output.push('\u{c}'),
+            Some('n') => output.push('\n'),
+            Some('r') => output.push('\r'),
+            Some('t') => output.push('\t'),
+            Some('u') => {
+                let code: String = chars.by_ref().take(4).collect();
+                let escaped = u32::from_str_radix(&code, 16).ok().and_then(char::from_u32);
+                output.push(escaped.unwrap_or(char::REPLACEMENT_CHARACTER));
+            }
+            Some(c) => output.push(c),
+            None => output.push('\\'),
+        }
+    }
+
+    output
+}
+
+// removes the indentation shared by all the lines of a multi-line string,
+// along with the line breaks right after the opening quotes and right before the closing ones
+fn strip_indentation(raw: &str) -> String {
+    let mut lines: Vec<&str> = raw.split('\n').collect();
+    if lines.len() > 1 && lines[0].trim().is_empty() {
+        lines.remove(0);
+    }
+    if lines.len() > 1 && lines[lines.len() - 1].trim().is_empty() {
+        lines.pop();
+    }
+
+    let indentation = lines
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.chars().take_while(|c| c.is_whitespace()).count())
+        .min()
+        .unwrap_or(0);
+
+    lines
+        .iter()
+        .map(|line| {
+            if line.trim().is_empty() {
+                ""
+            } else {
+                // the indentation is counted in chars since whitespace can take several bytes
+                let start = line.char_indices().nth(indentation).map_or(line.len(), |(i, _)| i);
+                &line[start..]
+            }
+        })
+        .collect::<Vec<&str>>()
+        .join("\n")
+}
+
+// replaces `{n}` with the element at depth n in the stack, `{{` and `}}` are literal braces
+fn interpolate(template: &str, stack: &Stack) -> Result<String, LexingError> {
+    let mut output = String::new();
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                output.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                output.push('}');
+            }
+            '{' => {
+                let mut placeholder = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => placeholder.push(c),
+                        None => {
+                            return Err(LexingError::InvalidInterpolation(format!(
+                                "Unclosed placeholder {{{}",
+                                placeholder
+                            )))
+                        }
+                    }
+                }
+                let depth: usize = placeholder.trim().parse().map_err(|_| {
+                    LexingError::InvalidInterpolation(format!(
+                        "Invalid placeholder {{{}}}",
+                        placeholder
+                    ))
+                })?;
+                match stack.get(depth) {
+                    None => {
+                        return Err(LexingError::InvalidInterpolation(format!(
+                            "Placeholder {{{}}} is out of bound for stack of length {}",
+                            depth,
+                            stack.len()
+                        )))
+                    }
+                    Some(el) => output.push_str(&el.value.to_string()),
+                }
+            }
+            '}' => {
+                return Err(LexingError::InvalidInterpolation(String::from(
+                    "Unmatched closing brace",
+                )))
+            }
+            c => output.push(c),
+        }
+    }
+
+    Ok(output)
+}
+
+// extracts the value of any kind of string literal, interpolated strings read the stack without modifying it
+fn string_literal(token: &Token, slice: &str, stack: &Stack) -> Result<String, LexingError> {
+    match token {
+        Token::RawString => {
+            let hashes = slice.len() - slice.trim_start_matches(['r', '#']).len() - 1;
+            Ok(slice[hashes + 2..slice.len() - 1 - hashes].to_string())
+        }
+        Token::MultilineString => Ok(unescape_string(&strip_indentation(
+            &slice[3..slice.len() - 3],
+        ))),
+        Token::InterpolatedString => {
+            interpolate(&unescape_string(&slice[2..slice.len() - 1]), stack)
+        }
+        _ => Ok(unescape_string(&slice[1..slice.len() - 1])),
+    }
+}
+
+fn check_bytes(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
+    if lex.slice().len() % 2 == 1 {
+        return Err(LexingError::InvalidBytes(String::from(lex.slice())));
+    }
+
+    Ok(())
+}
+
+fn bytes_literal(slice: &str) -> Vec<u8> {
+    (2..slice.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&slice[i..i + 2], 16).unwrap())
+        .collect()
+}
+
+fn char_literal(slice: &str) -> char {
+    match &slice[1..slice.len() - 1] {
+        "\\0" => '\0',
+        escaped => unescape_string(escaped).chars().next().unwrap(),
+    }
+}
+
+// literals are parsed recursively, nesting them deeper could overflow the stack of the interpreter
+const MAX_LITERAL_DEPTH: usize = 100;
+
+// parses the elements of a vector literal, `src` starts right after the opening bracket
+// returns the elements and the length of the literal including the closing bracket.
+// `depth` is the number of literals the vector is nested in
+fn parse_vector_literal(
+    src: &str,
+    stack: Option<&Stack>,
+    depth: usize,
+) -> Result<(Vec<StackElValue>, usize), LexingError> {
+    let invalid = |pos: usize| match src[pos..].split_whitespace().next() {
+        None => LexingError::InvalidVector(String::from("Unterminated vector literal")),
+        Some(rest) => LexingError::InvalidVector(format!("Unexpected `{}` in vector literal", rest)),
+    };
+    let skip_whitespace = |pos: usize| pos + src[pos..].len() - src[pos..].trim_start().len();
+
+    let mut elements = vec![];
+    let mut pos = skip_whitespace(0);
+    loop {
+        if src[pos..].starts_with(']') {
+            return Ok((elements, pos + 1));
+        }
+
+        match parse_literal_element(&src[pos..], stack, depth)? {
+            None => return Err(invalid(pos)),
+            Some((element, len)) => {
+                elements.push(element);
+                pos += len;
+            }
+        }
+
+        pos = skip_whitespace(pos);
+        if src[pos..].starts_with(',') {
+            pos = skip_whitespace(pos + 1);
+        } else if !src[pos..].starts_with(']') {
+            return Err(invalid(pos));
+        }
+    }
+}
+
+// parses the fields of a record literal, e.g. `#{name: "bob", age: 42}`
+fn parse_record_literal(
+    src: &str,
+    stack: Option<&Stack>,
+    depth: usize,
+) -> Result<(Vec<(String, StackElValue)>, usize), LexingError> {
+    let invalid = |pos: usize| match src[pos..].split_whitespace().next() {
+        None => LexingError::InvalidRecord(String::from("Unterminated record literal")),
+        Some(rest) => LexingError::InvalidRecord(format!("Unexpected `{}` in record literal", rest)),
+    };
+    let skip_whitespace = |pos: usize| pos + src[pos..].len() - src[pos..].trim_start().len();
+
+    let mut fields = vec![];
+    let mut pos = skip_whitespace(0);
+    loop {
+        if src[pos..].starts_with('}') {
+            return Ok((fields, pos + 1));
+        }
+
+        let name_len = field_name_len(&src[pos..]).ok_or_else(|| invalid(pos))?;
+        let name = src[pos..pos + name_len].to_string();
+        pos = skip_whitespace(pos + name_len);
+        if !src[pos..].starts_with(':') {
+            return Err(invalid(pos));
+        }
+        pos = skip_whitespace(pos + 1);
+        match parse_literal_element(&src[pos..], stack, depth)? {
+            None => return Err(invalid(pos)),
+            Some((value, len)) => {
+                fields.push((name, value));
+                pos += len;
+            }
+        }
+
+        pos = skip_whitespace(pos);
+        if src[pos..].starts_with(',') {
+            pos = skip_whitespace(pos + 1);
+        } else if !src[pos..].starts_with('}') {
+            return Err(invalid(pos));
+        }
+    }
+}
+
+// field names start with a lowercase letter or an underscore so that they can't be mistaken for opcodes
+fn field_name_len(src: &str) -> Option<usize> {
+    if !src.starts_with(|c: char| c.is_ascii_lowercase() || c == '_') {
+        return None;
+    }
+
+    Some(
+        src.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .unwrap_or(src.len()),
+    )
+}
+
+// parses a value inside a vector or record literal, returns `None` if there is no value to parse.
+// without a stack the literal is only measured: interpolated strings are left empty
+fn parse_literal_element(
+    src: &str,
+    stack: Option<&Stack>,
+    depth: usize,
+) -> Result<Option<(StackElValue, usize)>, LexingError> {
+    let too_deep = || format!("Literals can't be nested more than {} levels deep", MAX_LITERAL_DEPTH);
+    if let Some(rest) = src.strip_prefix('[') {
+        if depth + 1 == MAX_LITERAL_DEPTH {
+            return Err(LexingError::InvalidVector(too_deep()));
+        }
+        let (nested, len) = parse_vector_literal(rest, stack, depth + 1)?;
+        let vector = build_vector(LifoVector::new(), nested)?;
+        return Ok(Some((StackElValue::Vector(vector), 1 + len)));
+    }
+    if let Some(rest) = src.strip_prefix("#{") {
+        if depth + 1 == MAX_LITERAL_DEPTH {
+            return Err(LexingError::InvalidRecord(too_deep()));
+        }
+        let (fields, len) = parse_record_literal(rest, stack, depth + 1)?;
+        let record = build_record(fields)?;
+        return Ok(Some((StackElValue::Record(record), 2 + len)));
+    }
+    if let Some(rest) = src.strip_prefix('{') {
+        let (code, len) = parse_quotation_literal(rest)?;
+        if stack.is_some() {
+            check_quotation(&code)?;
+        }
+        return Ok(Some((StackElValue::Quotation(code), 1 + len)));
+    }
+
+    // the element is only read, opcodes like `LOG` must not run
+    let mut element_lex = Token::lexer_with_extras(src, State::skipping());
+    let element = match element_lex.next() {
+        Some(Ok(Token::Int)) => StackElValue::Int(int_literal(element_lex.slice())?),
+        Some(Ok(Token::Bool)) => StackElValue::Bool(element_lex.slice().parse().unwrap()),
+        Some(Ok(Token::Char)) => StackElValue::Char(char_literal(element_lex.slice())),
+        Some(Ok(Token::Bytes)) => StackElValue::Bytes(bytes_literal(element_lex.slice())),
+        Some(Ok(
+            tk @ (Token::String
+            | Token::RawString
+            | Token::MultilineString
+            | Token::InterpolatedString),
+        )) => match stack {
+            None => StackElValue::String(String::new()),
+            Some(stack) => StackElValue::String(string_literal(&tk, element_lex.slice(), stack)?),
+        },
+        _ => return Ok(None),
+    };
+
+    Ok(Some((element, element_lex.span().end)))
+}
+
+// inserts the elements one by one so that they are type-checked like with `INSERT`
+fn build_vector(vector: LifoVector, elements: Vec<StackElValue>) -> Result<LifoVector, LexingError> {
+    elements
+        .into_iter()
+        .try_fold(vector, |vector, el| vector.insert(el))
+        .map_err(LexingError::InvalidVector)
+}
+
+fn build_record(fields: Vec<(String, StackElValue)>) -> Result<LifoRecord, LexingError> {
+    LifoRecord::from_fields(fields).map_err(LexingError::InvalidRecord)
+}
+
+// the stack used by the literals, skipped literals are only measured so that nothing is evaluated
+fn literal_stack(state: &State) -> Option<&Stack> {
+    match label_is_set(&state.current_label) {
+        true => None,
+        false => Some(&state.stack),
+    }
+}
+
+// the literal can be followed by the type of its elements, e.g. `[] int`
+fn vector_literal(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
+    let (elements, len) = parse_vector_literal(lex.remainder(), literal_stack(&lex.extras), 0)?;
+    lex.bump(len);
+    let annotation = type_annotation(lex);
+    lex.extras.literal = Some(Literal::Vector(elements, annotation));
+
+    Ok(())
+}
+
+// consumes the type name following the current token, if any
+fn type_annotation(lex: &mut Lexer<Token>) -> Option<Type> {
+    let remainder = lex.remainder();
+    let trimmed = remainder.trim_start();
+    let (annotation, len) = Type::parse(trimmed)?;
+    if trimmed[len..].starts_with(|c: char| c.is_alphanumeric() || c == '_' || c == ':') {
+        return None;
+    }
+    lex.bump(remainder.len() - trimmed.len() + len);
+
+    Some(annotation)
+}
+
+fn record_literal(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
+    let (fields, len) = parse_record_literal(lex.remainder(), literal_stack(&lex.extras), 0)?;
+    lex.bump(len);
+    lex.extras.literal = Some(Literal::Record(fields));
+
+    Ok(())
+}
+
+// finds the brace closing a quotation literal, `src` starts right after the opening brace
+// returns the code of the quotation and the length of the literal including the closing brace.
+// the quotation is only measured, see `check_quotation`
+fn parse_quotation_literal(src: &str) -> Result<(String, usize), LexingError> {
+    // nested quotations are consumed by their own literal so the first closing brace found is the right one
+    let mut lex = Token::lexer_with_extras(src, State::skipping());
+    while let Some(token) = lex.next() {
+        if let Ok(Token::QuotationEnd) = token {
+            let code = src[..lex.span().start].trim().to_string();
+            return Ok((code, lex.span().end));
+        }
+    }
+
+    Err(LexingError::InvalidQuotation(String::from("Unterminated quotation literal")))
+}
+
+// the code of a quotation literal read by a lexer, without its braces
+fn quotation_code(slice: &str) -> &str {
+    slice[1..slice.len() - 1].trim()
+}
+
+// checks the blocks of a quotation, then the ones of the quotations nested in it.
+// skipped code only measures its quotations so that each of them is checked once
+fn check_quotation(code: &str) -> Result<(), LexingError> {
+    block_table(code).map_err(|message| {
+        LexingError::InvalidQuotation(format!("{} in quotation `{{ {} }}`", message, code))
+    })?;
+
+    let mut lex = Token::lexer_with_extras(code, State::skipping());
+    while let Some(token) = lex.next() {
+        if let Ok(Token::QuotationLiteral) = token {
+            check_quotation(quotation_code(lex.slice()))?;
+        }
+    }
+
+    Ok(())
+}
+
+// unlike the other literals, quotations are pushed without `PUSH`, e.g. `{ DUP MUL }`
+fn quotation_literal(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
+    let (code, len) = parse_quotation_literal(lex.remainder())?;
+    lex.bump(len);
+
+    if label_is_set(&lex.extras.current_label) {
+        return Ok(());
+    }
+    check_quotation(&code)?;
+
+    lex.extras
+        .stack
+        .insert(0, StackEl::new(StackElValue::Quotation(code)));
+
+    Ok(())
+}
+
+fn quotation_end(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
+    if label_is_set(&lex.extras.current_label) {
+        return Ok(());
+    }
+
+    Err(LexingError::InvalidQuotation(String::from(
+        "Unexpected `}` without matching `{`",
+    )))
+}
+
+fn raw_string(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
+    let hashes = lex.slice().len() - 2;
+    let closing = format!("\"{}", "#".repeat(hashes));
+    match lex.remainder().find(&closing) {
+        None => Err(LexingError::InvalidString(String::from(lex.slice()))),
+        Some(len) => {
+            lex.bump(len + closing.len());
+            Ok(())
+        }
+    }
+}
+
+fn multiline_string(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
+    match lex.remainder().find("\"\"\"") {
+        None => Err(LexingError::InvalidString(String::from(lex.slice()))),
+        Some(len) => {
+            lex.bump(len + 3);
+            Ok(())
+        }
     }
 }
 
 #[derive(Default, Debug)]
 pub struct State {
     pub stack: Stack,
-    pub prev_op: Token,
     current_label: Option<String>,
+    // the last vector or record literal, built by `PUSH` when it follows the opcode
+    literal: Option<Literal>,
+    // the blocks of the whole program are checked once, before running it
+    // or when the first block opcode is reached
+    blocks_checked: bool,
+    // the blocks and the labels of the running code, the code is the program, a function or a quotation
+    code: Rc<CodeMap>,
+    // position of the running segment, e.g. the body of a loop, in the running code
+    offset: usize,
+    // maps of the functions and quotations that were run, they are computed once
+    code_maps: HashMap<String, Rc<CodeMap>>,
+    pub limits: Limits,
+    // bodies of the functions defined with `DEF`
+    functions: HashMap<String, String>,
+    // call stack, kept apart from the data stack: the names of the running functions and quotations.
+    // it holds no return addresses: the body of a frame is run by a nested lexer, and the caller's
+    // lexer resumes where it stopped once the frame is popped, so the return positions are kept
+    // by the host's own call stack
+    call_stack: Vec<String>,
+    // set by `RET` until the running function is left
+    returning: bool,
+    // steps of the execution taken so far, see `Limits::max_steps`
+    steps: usize,
+}
+
+#[derive(Debug)]
+enum Literal {
+    // the elements and the annotated type of the elements
+    Vector(Vec<StackElValue>, Option<Type>),
+    Record(Vec<(String, StackElValue)>),
+}
+
+// bounds the execution so that programs that never end are reported instead of hanging
+#[derive(Debug)]
+pub struct Limits {
+    // budget shared by the whole program, every loop iteration
+    // and every call of a function or a quotation takes a step
+    pub max_steps: usize,
+    pub max_call_depth: usize,
+}
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            max_steps: 1_000_000,
+            max_call_depth: 500,
+        }
+    }
+}
+impl State {
+    // state of a lexer that only reads the tokens without running them
+    fn skipping() -> Self {
+        State {
+            // labels can't be empty so the skipping never stops
+            current_label: Some(String::new()),
+            blocks_checked: true,
+            ..Default::default()
+        }
+    }
+
+    // state of a lexer running the whole source, the program is checked before any instruction runs
+    pub fn checked(src: &str) -> Result<Self, LexingError> {
+        let mut state = State::default();
+        state.check_program(src)?;
+
+        Ok(state)
+    }
+
+    // makes sure that every block of the program is closed, that `ELSE` only appears in `IF` blocks,
+    // that every `WHILE` has a `DO` and that the quotations are valid
+    fn check_program(&mut self, src: &str) -> Result<(), LexingError> {
+        self.code = Rc::new(CodeMap::new(src)?);
+        self.blocks_checked = true;
+
+        Ok(())
+    }
 }
 
 #[derive(Default, Debug, Clone, PartialEq)]
@@ -34,9 +553,80 @@ pub enum LexingError {
     SwapError(String),
     ConcatError(String),
     JumpiError(String),
+    JumpDynError(String),
+    SwitchError(String),
     InsertError(String),
     SizeError(String),
     IndexError(String),
+    MatchError(String),
+    CaptureError(String),
+    RegexReplaceError(String),
+    ToStrError(String),
+    ParseIntError(String),
+    ParseBoolError(String),
+    InvalidInterpolation(String),
+    ExplodeError(String),
+    ImplodeError(String),
+    IsDigitError(String),
+    IsAlphaError(String),
+    ToUpperError(String),
+    InvalidBytes(String),
+    SliceError(String),
+    BytesToIntError(String),
+    IntToBytesError(String),
+    BytesToStrError(String),
+    StrToBytesError(String),
+    SetError(String),
+    RemoveError(String),
+    InsertAtError(String),
+    PopBackError(String),
+    InvalidVector(String),
+    SortError(String),
+    ReverseError(String),
+    ContainsError(String),
+    IndexOfError(String),
+    EmptyMapError(String),
+    PutError(String),
+    GetError(String),
+    DelError(String),
+    HasError(String),
+    KeysError(String),
+    ValuesError(String),
+    EmptySetError(String),
+    UnionError(String),
+    IntersectError(String),
+    DiffError(String),
+    ToSetError(String),
+    ToVectorError(String),
+    PairError(String),
+    UnpairError(String),
+    CarError(String),
+    CdrError(String),
+    InvalidRecord(String),
+    GetFieldError(String),
+    SetFieldError(String),
+    IndexOptError(String),
+    SomeError(String),
+    UnwrapError(String),
+    IfNoneError(String),
+    LeftError(String),
+    RightError(String),
+    IfLeftError(String),
+    TypeOfError(String),
+    CompareError(String),
+    InvalidBlock(String),
+    IfError(String),
+    WhileError(String),
+    RepeatError(String),
+    DefError(String),
+    CallError(String),
+    RetError(String),
+    InvalidQuotation(String),
+    ExecError(String),
+    MapError(String),
+    FilterError(String),
+    FoldError(String),
+    EachError(String),
     #[default]
     Unknown,
 }
@@ -67,15 +657,42 @@ pub enum Token {
     #[regex("PUSH_STR", op_push_str)]
     PushStr,
 
-    #[regex("[0-9]+", priority = 1)]
+    #[regex("-?[0-9]+", priority = 1)]
     Int,
 
     #[regex("true|false")]
     Bool,
 
-    #[regex(r#""([^"\\]|\\["\\bnfrt]|u[a-fA-F0-9]{4})*""#)]
+    #[regex("0x[0-9a-fA-F]*", check_bytes)]
+    Bytes,
+
+    #[regex(r#""([^"\\]|\\["\\bnfrt]|\\u[a-fA-F0-9]{4})*""#)]
     String,
 
+    #[regex(r"'([^'\\]|\\['\\nrt0])'")]
+    Char,
+
+    #[token("[", vector_literal)]
+    VectorLiteral,
+
+    #[token("#{", record_literal)]
+    RecordLiteral,
+
+    #[token("{", quotation_literal)]
+    QuotationLiteral,
+
+    #[token("}", quotation_end)]
+    QuotationEnd,
+
+    #[regex(r##"r#*""##, raw_string)]
+    RawString,
+
+    #[token(r#"""""#, multiline_string)]
+    MultilineString,
+
+    #[regex(r#"\$"([^"\\]|\\["\\bnfrt]|\\u[a-fA-F0-9]{4})*""#)]
+    InterpolatedString,
+
     #[regex("DUP", op_dup)]
     Dup,
 
@@ -100,18 +717,231 @@ pub enum Token {
     #[regex("JUMPI", op_jumpi)]
     Jumpi,
 
+    #[regex("JUMP_DYN", op_jump_dyn)]
+    JumpDyn,
+
+    #[regex("SWITCH", op_switch)]
+    Switch,
+
     #[regex("EMPTY_VECTOR", op_empty_vector)]
     EmptyVector,
 
     #[regex("INSERT", op_insert_vector)]
     InsertVector,
 
+    #[regex("SET", op_set)]
+    Set,
+
+    #[regex("REMOVE", op_remove)]
+    Remove,
+
+    #[regex("INSERT_AT", op_insert_at)]
+    InsertAt,
+
+    #[regex("POP_BACK", op_pop_back)]
+    PopBack,
+
+    #[regex("SORT", op_sort)]
+    Sort,
+
+    #[regex("REVERSE", op_reverse)]
+    Reverse,
+
+    #[regex("CONTAINS", op_contains)]
+    Contains,
+
+    #[regex("INDEX_OF", op_index_of)]
+    IndexOf,
+
+    #[regex("EMPTY_MAP", op_empty_map)]
+    EmptyMap,
+
+    #[regex("PUT", op_put)]
+    Put,
+
+    #[regex("GET", op_get)]
+    Get,
+
+    #[regex("DEL", op_del)]
+    Del,
+
+    #[regex("HAS", op_has)]
+    Has,
+
+    #[regex("KEYS", op_keys)]
+    Keys,
+
+    #[regex("VALUES", op_values)]
+    Values,
+
+    #[regex("EMPTY_SET", op_empty_set)]
+    EmptySet,
+
+    #[regex("UNION", op_union)]
+    Union,
+
+    #[regex("INTERSECT", op_intersect)]
+    Intersect,
+
+    #[regex("DIFF", op_diff)]
+    Diff,
+
+    #[regex("TO_SET", op_to_set)]
+    ToSet,
+
+    #[regex("TO_VECTOR", op_to_vector)]
+    ToVector,
+
+    #[regex("PAIR", op_pair)]
+    Pair,
+
+    #[regex("UNPAIR", op_unpair)]
+    Unpair,
+
+    #[regex("CAR", op_car)]
+    Car,
+
+    #[regex("CDR", op_cdr)]
+    Cdr,
+
+    #[regex("GET_FIELD", op_get_field)]
+    GetField,
+
+    #[regex("SET_FIELD", op_set_field)]
+    SetField,
+
+    #[regex("INDEX_OPT", op_index_opt)]
+    IndexOpt,
+
+    #[regex("SOME", op_some)]
+    Some,
+
+    #[regex("NONE", op_none)]
+    None,
+
+    #[regex("UNWRAP", op_unwrap)]
+    Unwrap,
+
+    #[regex("IF_NONE", op_if_none)]
+    IfNone,
+
+    #[regex("LEFT", |lex| op_or(lex, true))]
+    Left,
+
+    #[regex("RIGHT", |lex| op_or(lex, false))]
+    Right,
+
+    #[regex("IF_LEFT", op_if_left)]
+    IfLeft,
+
+    #[regex("IF", op_if)]
+    If,
+
+    #[regex("ELSE", op_else)]
+    Else,
+
+    #[regex("END", op_end)]
+    End,
+
+    #[regex("WHILE", op_while)]
+    While,
+
+    #[regex("DO", op_do)]
+    Do,
+
+    #[regex("REPEAT", op_repeat)]
+    Repeat,
+
+    #[regex("DEF", op_def)]
+    Def,
+
+    #[regex("CALL", op_call)]
+    Call,
+
+    #[regex("RET", op_ret)]
+    Ret,
+
+    #[regex("EXEC", op_exec)]
+    Exec,
+
+    #[regex("MAP", op_map)]
+    Map,
+
+    #[regex("FILTER", op_filter)]
+    Filter,
+
+    #[regex("FOLD", op_fold)]
+    Fold,
+
+    #[regex("EACH", op_each)]
+    Each,
+
+    #[regex("COMPARE", op_compare)]
+    Compare,
+
+    #[regex("TYPEOF", op_type_of)]
+    TypeOf,
+
     #[regex("SIZE", op_size)]
     Size,
 
     #[regex("INDEX", op_index)]
     Index,
 
+    #[regex("MATCH", op_match)]
+    Match,
+
+    #[regex("CAPTURE", op_capture)]
+    Capture,
+
+    #[regex("REGEX_REPLACE", op_regex_replace)]
+    RegexReplace,
+
+    #[regex("TO_STR", op_to_str)]
+    ToStr,
+
+    #[regex("PARSE_INT", op_parse_int)]
+    ParseInt,
+
+    #[regex("PARSE_BOOL", op_parse_bool)]
+    ParseBool,
+
+    #[regex("EXPLODE", op_explode)]
+    Explode,
+
+    #[regex("IMPLODE", op_implode)]
+    Implode,
+
+    #[regex("IS_DIGIT", op_is_digit)]
+    IsDigit,
+
+    #[regex("IS_ALPHA", op_is_alpha)]
+    IsAlpha,
+
+    #[regex("TO_UPPER", op_to_upper)]
+    ToUpper,
+
+    #[regex("SLICE", op_slice)]
+    Slice,
+
+    #[regex("BYTES_TO_INT_BE", |lex| op_bytes_to_int(lex, true))]
+    BytesToIntBe,
+
+    #[regex("BYTES_TO_INT_LE", |lex| op_bytes_to_int(lex, false))]
+    BytesToIntLe,
+
+    #[regex("INT_TO_BYTES_BE", |lex| op_int_to_bytes(lex, true))]
+    IntToBytesBe,
+
+    #[regex("INT_TO_BYTES_LE", |lex| op_int_to_bytes(lex, false))]
+    IntToBytesLe,
+
+    #[regex("BYTES_TO_STR", op_bytes_to_str)]
+    BytesToStr,
+
+    #[regex("STR_TO_BYTES", op_str_to_bytes)]
+    StrToBytes,
+
     #[regex("LOG", op_log)]
     Log,
 
@@ -155,43 +985,89 @@ pub enum Token {
     Invalid,
 }
 
+// ints are 128 bits wide, longer literals are rejected instead of wrapping
+fn int_literal(slice: &str) -> Result<i128, LexingError> {
+    slice.parse().map_err(|_| {
+        LexingError::InvalidInteger(format!("Integer literal {} doesn't fit in an int", slice))
+    })
+}
+
 fn invalid_token(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
-    if lex.extras.prev_op == Token::Jump {
-        Err(LexingError::InvalidLabel(lex.slice().to_string()))
-    } else {
-        Err(LexingError::InvalidToken(lex.slice().to_string()))
-    }
+    Err(LexingError::InvalidToken(lex.slice().to_string()))
 }
 
+// all the push opcodes put their value on top of the stack.
+// `PUSH` accepts any literal and can be given the type of the value first, e.g. `PUSH int 5`
 fn op_push(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
+    // the ascription is consumed even when the instruction is skipped
+    let ascription = type_annotation(lex);
+
     if label_is_set(&lex.extras.current_label) {
         return Ok(());
     }
 
-    match lex.next() {
-        Some(Ok(Token::Int)) => {
-            let val = lex.slice().parse().unwrap();
-            lex.extras
-                .stack
-                .insert(0, StackEl::new(Token::Int, StackElValue::Int(val)));
-            Ok(())
+    let value = match lex.next() {
+        Some(Ok(Token::Int)) => StackElValue::Int(int_literal(lex.slice())?),
+        Some(Ok(Token::Bool)) => StackElValue::Bool(lex.slice().parse().unwrap()),
+        Some(Ok(Token::Bytes)) => StackElValue::Bytes(bytes_literal(lex.slice())),
+        // vector and record literals are parsed by their token callback
+        Some(Ok(Token::VectorLiteral)) => {
+            let (elements, annotation) = match lex.extras.literal.take() {
+                Some(Literal::Vector(elements, annotation)) => (elements, annotation),
+                _ => (vec![], None),
+            };
+            let el_type = match (&ascription, annotation) {
+                (_, Some(el_type)) => el_type,
+                // `PUSH vector<int> []` gives the type of the elements like `PUSH [] int`
+                (Some(Type::Vector(el_type)), None) => *el_type.clone(),
+                _ => Type::Unknown,
+            };
+            let val = build_vector(LifoVector::of(el_type), elements)?;
+            StackElValue::Vector(val)
         }
-        Some(Ok(Token::Bool)) => {
-            let val = lex.slice().parse().unwrap();
-            lex.extras
-                .stack
-                .insert(0, StackEl::new(Token::Bool, StackElValue::Bool(val)));
-            Ok(())
+        Some(Ok(Token::RecordLiteral)) => {
+            let fields = match lex.extras.literal.take() {
+                Some(Literal::Record(fields)) => fields,
+                _ => vec![],
+            };
+            StackElValue::Record(build_record(fields)?)
         }
-        Some(Ok(Token::String)) => {
-            let str_value = &lex.slice()[1..lex.slice().len() - 1];
-            lex.extras
-                .stack
-                .insert(0, StackEl::new(Token::String, StackElValue::String(str_value.to_string())));
-            Ok(())
+        Some(Ok(Token::Char)) => StackElValue::Char(char_literal(lex.slice())),
+        Some(Ok(
+            tk @ (Token::String
+            | Token::RawString
+            | Token::MultilineString
+            | Token::InterpolatedString),
+        )) => {
+            let str_value = string_literal(&tk, lex.slice(), &lex.extras.stack)?;
+            StackElValue::String(str_value)
+        }
+        // the quotation literal pushes itself, it is taken back so that the ascription is checked
+        Some(Ok(Token::QuotationLiteral)) => lex.extras.stack.remove(0).value,
+        // syntax errors inside literals are more helpful than a generic push error
+        Some(Err(
+            err @ (LexingError::InvalidVector(_)
+            | LexingError::InvalidRecord(_)
+            | LexingError::InvalidInteger(_)
+            | LexingError::InvalidQuotation(_)),
+        )) => return Err(err),
+        _ => return Err(LexingError::InvalidPush(String::from(lex.slice()))),
+    };
+
+    if let Some(ascription) = ascription {
+        // every value can be pushed as `any`
+        if ascription != Type::Any && ascription.unify(&value.get_type()).is_none() {
+            return Err(LexingError::InvalidPush(format!(
+                "Cannot push value of type {} as type {}",
+                value.type_name(),
+                ascription
+            )));
         }
-        _ => Err(LexingError::InvalidPush(String::from(lex.slice()))),
     }
+
+    lex.extras.stack.insert(0, StackEl::new(value));
+
+    Ok(())
 }
 
 fn op_push_int(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
@@ -201,10 +1077,10 @@ fn op_push_int(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
 
     match lex.next() {
         Some(Ok(Token::Int)) => {
-            let val = lex.slice().parse().unwrap();
+            let val = int_literal(lex.slice())?;
             lex.extras
                 .stack
-                .push(StackEl::new(Token::Int, StackElValue::Int(val)));
+                .insert(0, StackEl::new(StackElValue::Int(val)));
             Ok(())
         }
         _ => Err(LexingError::InvalidInteger(String::from(lex.slice()))),
@@ -217,11 +1093,16 @@ fn op_push_str(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
     }
     
     match lex.next() {
-        Some(Ok(Token::String)) => {
-            let str_value = &lex.slice()[1..lex.slice().len() - 1];
+        Some(Ok(
+            tk @ (Token::String
+            | Token::RawString
+            | Token::MultilineString
+            | Token::InterpolatedString),
+        )) => {
+            let str_value = string_literal(&tk, lex.slice(), &lex.extras.stack)?;
             lex.extras
                 .stack
-                .push(StackEl::new(Token::String, StackElValue::String(str_value.to_string())));
+                .insert(0, StackEl::new(StackElValue::String(str_value)));
             Ok(())
         }
         _ => Err(LexingError::InvalidString(String::from(lex.slice()))),
@@ -238,7 +1119,7 @@ fn op_push_bool(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
             let val = lex.slice().parse().unwrap();
             lex.extras
                 .stack
-                .push(StackEl::new(Token::Bool, StackElValue::Bool(val)));
+                .insert(0, StackEl::new(StackElValue::Bool(val)));
             Ok(())
         }
         _ => Err(LexingError::InvalidBool(String::from(lex.slice()))),
@@ -371,92 +1252,1447 @@ fn op_concat(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
     }
 }
 
-fn op_jump(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
-    if label_is_set(&lex.extras.current_label) {
-        return Ok(());
-    }
-    
-    lex.extras.prev_op = Token::Jump;
-
-    match lex.next() {
-        Some(Ok(Token::LabelName)) => {
-            let label = lex.slice();
-            lex.extras.current_label = Some(label.to_string());
-            Ok(())
+// reads the block opcodes of the source with their position, without running anything
+fn block_tokens(src: &str) -> Vec<(Token, Range<usize>)> {
+    let mut lex = Token::lexer_with_extras(src, State::skipping());
+    let mut tokens = vec![];
+    while let Some(token) = lex.next() {
+        if let Ok(
+            tk @ (Token::If
+            | Token::Else
+            | Token::While
+            | Token::Do
+            | Token::Repeat
+            | Token::Def
+            | Token::End),
+        ) = token
+        {
+            tokens.push((tk, lex.span()));
         }
-        _ => Err(LexingError::InvalidLabel(String::from(lex.slice()))),
+    }
+
+    tokens
+}
+
+// name of the opcode opening the block
+fn block_name(tk: &Token) -> &'static str {
+    match tk {
+        Token::If => "IF",
+        Token::While => "WHILE",
+        Token::Def => "DEF",
+        _ => "REPEAT",
+    }
+}
+
+// the program is checked by the first block opcode when it wasn't checked before running it
+fn check_blocks(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
+    if lex.extras.blocks_checked {
+        return Ok(());
+    }
+
+    let src = lex.source();
+    lex.extras.check_program(src)
+}
+
+// what is known about a piece of code before running it
+#[derive(Default, Debug)]
+struct CodeMap {
+    blocks: BlockTable,
+    // the labels with their position, the computed jumps can only go to the ones after them
+    labels: Vec<(String, usize)>,
+}
+impl CodeMap {
+    fn new(src: &str) -> Result<Self, LexingError> {
+        // invalid quotations are read as code by the block table, they are reported first
+        let labels = code_labels(src)?;
+        let blocks = block_table(src).map_err(LexingError::InvalidBlock)?;
+
+        Ok(CodeMap { blocks, labels })
+    }
+}
+
+// the labels defined in the code, the ones in quotations are local to the quotations
+fn code_labels(src: &str) -> Result<Vec<(String, usize)>, LexingError> {
+    let mut lex = Token::lexer_with_extras(src, State::skipping());
+    let mut labels = vec![];
+    while let Some(token) = lex.next() {
+        match token {
+            Ok(Token::Label) => {
+                let label = lex.slice().trim_end_matches(':').to_string();
+                labels.push((label, lex.span().start));
+            }
+            Ok(Token::QuotationLiteral) => check_quotation(quotation_code(lex.slice()))?,
+            Err(err @ LexingError::InvalidQuotation(_)) => return Err(err),
+            _ => (),
+        }
+    }
+
+    Ok(labels)
+}
+
+// the `ELSE` or `DO` and the `END` of the block opened at each position
+type BlockTable = HashMap<usize, (Option<Range<usize>>, Range<usize>)>;
+
+// checks the blocks of the source and finds where each of them ends, the `ELSE` and `DO` opcodes
+// are mapped to the `END` of their block too.
+// quotations are left out, their blocks are checked by `check_quotation`
+fn block_table(src: &str) -> Result<BlockTable, String> {
+    let mut table = BlockTable::new();
+    // the opened blocks with their position, and the position of their `ELSE` or `DO`
+    let mut opened: Vec<(Token, Range<usize>, Option<Range<usize>>)> = vec![];
+    let invalid =
+        |message: &str, span: &Range<usize>| format!("{} at {}..{}", message, span.start, span.end);
+    for (tk, span) in block_tokens(src) {
+        match (tk, opened.last_mut()) {
+            (Token::Else, Some((Token::If, _, middle @ None))) => *middle = Some(span),
+            (Token::Do, Some((Token::While, _, middle @ None))) => *middle = Some(span),
+            (Token::Else, _) => return Err(invalid("ELSE without matching IF", &span)),
+            (Token::Do, _) => return Err(invalid("DO without matching WHILE", &span)),
+            (Token::End, Some((Token::While, while_span, None))) => {
+                return Err(invalid("WHILE without matching DO", while_span))
+            }
+            (Token::End, Some(_)) => {
+                let (_, block_span, middle) = opened.pop().unwrap_or_default();
+                if let Some(middle) = &middle {
+                    table.insert(middle.start, (None, span.clone()));
+                }
+                table.insert(block_span.start, (middle, span));
+            }
+            (Token::End, None) => return Err(invalid("END without matching block", &span)),
+            (tk, _) => opened.push((tk, span, None)),
+        }
+    }
+    if let Some((tk, span, _)) = opened.last() {
+        let message = format!("{} without matching END", block_name(tk));
+        return Err(invalid(&message, span));
+    }
+
+    Ok(table)
+}
+
+// finds the `ELSE` or `DO` and the `END` of the block opened by the current token,
+// their positions are relative to the remainder of the lexer
+fn block_bounds(lex: &Lexer<Token>) -> Result<(Option<Range<usize>>, Range<usize>), LexingError> {
+    let (middle, end) = lex
+        .extras
+        .code
+        .blocks
+        .get(&(lex.extras.offset + lex.span().start))
+        .ok_or(LexingError::InvalidBlock(String::from("Block without matching END")))?;
+    let base = lex.extras.offset + lex.span().end;
+    let relative = |span: &Range<usize>| span.start - base..span.end - base;
+
+    Ok((middle.as_ref().map(relative), relative(end)))
+}
+
+// runs the instructions up to `ELSE` or `END` if the boolean on top of the stack is true,
+// the instructions between `ELSE` and `END` otherwise
+fn op_if(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
+    check_blocks(lex)?;
+
+    if label_is_set(&lex.extras.current_label) {
+        return Ok(());
+    }
+
+    let condition = match lex.extras.stack.first().map(|el| &el.value) {
+        None => return Err(LexingError::IfError(String::from("Stack must be at least 1 element deep"))),
+        Some(StackElValue::Bool(val)) => *val,
+        Some(_) => return Err(LexingError::IfError(String::from("Top element must be a boolean value"))),
+    };
+    lex.extras.stack.remove(0);
+
+    if !condition {
+        let (else_span, end_span) = block_bounds(lex)?;
+        lex.bump(else_span.unwrap_or(end_span).end);
+    }
+
+    Ok(())
+}
+
+// reached after running the instructions of the true branch, the false branch is skipped
+fn op_else(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
+    check_blocks(lex)?;
+
+    if label_is_set(&lex.extras.current_label) {
+        return Ok(());
+    }
+
+    let (_, end_span) = block_bounds(lex)?;
+    lex.bump(end_span.end);
+
+    Ok(())
+}
+
+fn op_end(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
+    check_blocks(lex)
+}
+
+// runs a part of the source with the state of the lexer, e.g. the body of a loop
+fn run_segment(lex: &mut Lexer<Token>, segment: Range<usize>) -> Result<(), LexingError> {
+    let offset = lex.extras.offset + segment.start;
+    let src = lex.source();
+    run_code(lex, &src[segment], offset)
+}
+
+// runs the code with the state of the lexer, `offset` is its position in the running code
+fn run_code(lex: &mut Lexer<Token>, src: &str, offset: usize) -> Result<(), LexingError> {
+    let mut extras = std::mem::take(&mut lex.extras);
+    let parent_offset = std::mem::replace(&mut extras.offset, offset);
+    let mut segment_lex = Token::lexer_with_extras(src, extras);
+    let mut result = Ok(());
+    while let Some(token) = segment_lex.next() {
+        if let Err(err) = token {
+            result = Err(err);
+            break;
+        }
+        if segment_lex.extras.returning {
+            break;
+        }
+    }
+    lex.extras = segment_lex.extras;
+    lex.extras.offset = parent_offset;
+
+    result
+}
+
+// nested loops and recursive calls take their steps from the same budget so that they are bounded too
+fn take_step(state: &mut State, error: fn(String) -> LexingError) -> Result<(), LexingError> {
+    if state.steps == state.limits.max_steps {
+        return Err(error(format!(
+            "Execution stopped after {} steps, the program may never end",
+            state.steps
+        )));
+    }
+    state.steps += 1;
+
+    Ok(())
+}
+
+// a jump to a label after the loop or a `RET` leaves the loop
+fn interrupted(state: &State) -> bool {
+    label_is_set(&state.current_label) || state.returning
+}
+
+// runs the body as long as the condition leaves `true` on top of the stack, the boolean is consumed
+fn op_while(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
+    check_blocks(lex)?;
+
+    if label_is_set(&lex.extras.current_label) {
+        return Ok(());
+    }
+
+    let (do_span, end_span) = block_bounds(lex)?;
+    let do_span = do_span.ok_or(LexingError::InvalidBlock(String::from(
+        "WHILE without matching DO",
+    )))?;
+    let start = lex.span().end;
+    let condition = start..start + do_span.start;
+    let body = start + do_span.end..start + end_span.start;
+
+    loop {
+        run_segment(lex, condition.clone())?;
+        if interrupted(&lex.extras) {
+            break;
+        }
+        match lex.extras.stack.first().map(|el| &el.value) {
+            Some(StackElValue::Bool(true)) => lex.extras.stack.remove(0),
+            Some(StackElValue::Bool(false)) => {
+                lex.extras.stack.remove(0);
+                break;
+            }
+            _ => {
+                return Err(LexingError::WhileError(String::from(
+                    "The condition must leave a boolean value on top of the stack",
+                )))
+            }
+        };
+
+        take_step(&mut lex.extras, LexingError::WhileError)?;
+        run_segment(lex, body.clone())?;
+        if interrupted(&lex.extras) {
+            break;
+        }
+    }
+    lex.bump(end_span.end);
+
+    Ok(())
+}
+
+// stores the body of the function until `END`, the body is run by `CALL`
+fn op_def(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
+    let name = name_argument(lex);
+    check_blocks(lex)?;
+
+    if label_is_set(&lex.extras.current_label) {
+        return Ok(());
+    }
+
+    let name = name.map_err(|word| {
+        LexingError::DefError(format!("Invalid function name `{}`", word))
+    })?;
+    let (_, end_span) = block_bounds(lex)?;
+    lex.extras
+        .functions
+        .insert(name, lex.remainder()[..end_span.start].to_string());
+    lex.bump(end_span.end);
+
+    Ok(())
+}
+
+// functions must be defined before they are called, they can call themselves
+fn op_call(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
+    let name = name_argument(lex);
+
+    if label_is_set(&lex.extras.current_label) {
+        return Ok(());
+    }
+
+    let name = name.map_err(|word| {
+        LexingError::CallError(format!("Invalid function name `{}`", word))
+    })?;
+    let body = match lex.extras.functions.get(&name) {
+        None => return Err(LexingError::CallError(format!("Unknown function `{}`", name))),
+        Some(body) => body.clone(),
+    };
+
+    run_body(lex, format!("function `{}`", name), &body, LexingError::CallError)
+}
+
+// runs the body of a function or a quotation in a new frame of the call stack
+fn run_body(
+    lex: &mut Lexer<Token>,
+    frame: String,
+    body: &str,
+    error: fn(String) -> LexingError,
+) -> Result<(), LexingError> {
+    if lex.extras.call_stack.len() == lex.extras.limits.max_call_depth {
+        return Err(error(format!(
+            "Call depth limit of {} reached when calling {}, the recursion may never end",
+            lex.extras.limits.max_call_depth, frame
+        )));
+    }
+    take_step(&mut lex.extras, error)?;
+
+    let code = match lex.extras.code_maps.get(body) {
+        Some(code) => code.clone(),
+        None => {
+            let code = Rc::new(CodeMap::new(body)?);
+            lex.extras.code_maps.insert(body.to_string(), code.clone());
+            code
+        }
+    };
+    let parent_code = std::mem::replace(&mut lex.extras.code, code);
+
+    lex.extras.call_stack.push(frame);
+    let result = run_code(lex, body, 0);
+    let frame = lex.extras.call_stack.pop().unwrap_or_default();
+    lex.extras.code = parent_code;
+    lex.extras.returning = false;
+    result?;
+
+    // the labels of a function or a quotation are local to its body
+    if let Some(label) = &lex.extras.current_label {
+        return Err(error(format!("Label `{}` not found in {}", label, frame)));
+    }
+
+    Ok(())
+}
+
+// runs the quotation on top of the stack against the rest of the stack
+fn op_exec(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
+    // the blocks of the program are checked before running any code that could set the flag
+    check_blocks(lex)?;
+
+    if label_is_set(&lex.extras.current_label) {
+        return Ok(());
+    }
+
+    let code = match lex.extras.stack.first().map(|el| &el.value) {
+        None => return Err(LexingError::ExecError(String::from("Stack must be at least 1 element deep"))),
+        Some(StackElValue::Quotation(code)) => code.clone(),
+        Some(val) => {
+            return Err(LexingError::ExecError(format!(
+                "Cannot execute element of type {}",
+                val.type_name()
+            )))
+        }
+    };
+    lex.extras.stack.remove(0);
+
+    run_body(lex, String::from("a quotation"), &code, LexingError::ExecError)
+}
+
+// reads the block on top of the stack and the vector `depth` elements deep,
+// the arguments of the higher-order opcodes
+fn block_and_vector(stack: &Stack, depth: usize) -> Result<(String, LifoVector), String> {
+    if stack.len() < depth {
+        return Err(format!("Stack must be at least {} elements deep", depth));
+    }
+
+    match (&stack[0].value, &stack[depth - 1].value) {
+        (StackElValue::Quotation(code), StackElValue::Vector(vector)) => {
+            Ok((code.clone(), vector.clone()))
+        }
+        (StackElValue::Quotation(_), val) => {
+            Err(format!("Cannot iterate over element of type {}", val.type_name()))
+        }
+        (val, _) => Err(format!("Cannot execute element of type {}", val.type_name())),
+    }
+}
+
+// runs the block with its arguments on top of the stack, the last one on top,
+// the block must replace them with a single value which is returned
+fn apply_block(
+    lex: &mut Lexer<Token>,
+    code: &str,
+    args: Vec<StackElValue>,
+    error: fn(String) -> LexingError,
+) -> Result<StackElValue, LexingError> {
+    let depth = lex.extras.stack.len();
+    for arg in args {
+        lex.extras.stack.insert(0, StackEl::new(arg));
+    }
+    run_body(lex, String::from("a quotation"), code, error)?;
+    if lex.extras.stack.len() != depth + 1 {
+        return Err(error(String::from(
+            "The block must replace its arguments with exactly one value",
+        )));
+    }
+
+    Ok(lex.extras.stack.remove(0).value)
+}
+
+// replaces every element of the vector with the value the block leaves for it,
+// the type of the new vector is the type of these values, `any` when their types differ
+fn op_map(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
+    check_blocks(lex)?;
+
+    if label_is_set(&lex.extras.current_label) {
+        return Ok(());
+    }
+
+    let (code, vector) = block_and_vector(&lex.extras.stack, 2).map_err(LexingError::MapError)?;
+    lex.extras.stack.drain(..2);
+
+    let mut values = vec![];
+    for el in vector.elements {
+        values.push(apply_block(lex, &code, vec![el], LexingError::MapError)?);
+    }
+    let el_type = values
+        .iter()
+        .try_fold(Type::Unknown, |el_type, value| el_type.unify(&value.get_type()))
+        .unwrap_or(Type::Any);
+    let mapped = LifoVector::from_elements(el_type, values);
+    lex.extras
+        .stack
+        .insert(0, StackEl::new(StackElValue::Vector(mapped)));
+
+    Ok(())
+}
+
+// keeps the elements of the vector for which the block leaves `true`
+fn op_filter(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
+    check_blocks(lex)?;
+
+    if label_is_set(&lex.extras.current_label) {
+        return Ok(());
+    }
+
+    let (code, vector) =
+        block_and_vector(&lex.extras.stack, 2).map_err(LexingError::FilterError)?;
+    lex.extras.stack.drain(..2);
+
+    let mut kept = vec![];
+    for el in vector.elements {
+        match apply_block(lex, &code, vec![el.clone()], LexingError::FilterError)? {
+            StackElValue::Bool(true) => kept.push(el),
+            StackElValue::Bool(false) => (),
+            val => {
+                return Err(LexingError::FilterError(format!(
+                    "The block must leave a boolean value, found {}",
+                    val.type_name()
+                )))
+            }
+        }
+    }
+    let filtered = LifoVector::from_elements(vector.element_type, kept);
+    lex.extras
+        .stack
+        .insert(0, StackEl::new(StackElValue::Vector(filtered)));
+
+    Ok(())
+}
+
+// the initial accumulator is right below the block, the vector below it.
+// the block is given the accumulator and the element on top of it, and leaves the new accumulator
+fn op_fold(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
+    check_blocks(lex)?;
+
+    if label_is_set(&lex.extras.current_label) {
+        return Ok(());
+    }
+
+    let (code, vector) = block_and_vector(&lex.extras.stack, 3).map_err(LexingError::FoldError)?;
+    let mut accumulator = lex.extras.stack[1].value.clone();
+    lex.extras.stack.drain(..3);
+
+    for el in vector.elements {
+        accumulator = apply_block(lex, &code, vec![accumulator, el], LexingError::FoldError)?;
+    }
+    lex.extras.stack.insert(0, StackEl::new(accumulator));
+
+    Ok(())
+}
+
+// runs the block with every element of the vector on top of the stack,
+// what the block leaves on the stack is kept
+fn op_each(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
+    check_blocks(lex)?;
+
+    if label_is_set(&lex.extras.current_label) {
+        return Ok(());
+    }
+
+    let (code, vector) = block_and_vector(&lex.extras.stack, 2).map_err(LexingError::EachError)?;
+    lex.extras.stack.drain(..2);
+
+    for el in vector.elements {
+        lex.extras.stack.insert(0, StackEl::new(el));
+        run_body(lex, String::from("a quotation"), &code, LexingError::EachError)?;
+    }
+
+    Ok(())
+}
+
+// leaves the running function or quotation, the rest of its body is not run
+fn op_ret(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
+    if label_is_set(&lex.extras.current_label) {
+        return Ok(());
+    }
+
+    if lex.extras.call_stack.is_empty() {
+        return Err(LexingError::RetError(String::from("RET can only be used in a function or a quotation")));
+    }
+    lex.extras.returning = true;
+
+    Ok(())
+}
+
+// only reached when jumping into a loop condition, the loop is not run
+fn op_do(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
+    check_blocks(lex)?;
+
+    if label_is_set(&lex.extras.current_label) {
+        return Ok(());
+    }
+
+    let (_, end_span) = block_bounds(lex)?;
+    lex.bump(end_span.end);
+
+    Ok(())
+}
+
+// runs the body as many times as the int on top of the stack, the int is consumed
+fn op_repeat(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
+    check_blocks(lex)?;
+
+    if label_is_set(&lex.extras.current_label) {
+        return Ok(());
+    }
+
+    let count = match lex.extras.stack.first().map(|el| &el.value) {
+        None => {
+            return Err(LexingError::RepeatError(String::from(
+                "Stack must be at least 1 element deep",
+            )))
+        }
+        Some(StackElValue::Int(count)) => *count,
+        Some(val) => {
+            return Err(LexingError::RepeatError(format!(
+                "The number of repetitions must be of type int, found {}",
+                val.type_name()
+            )))
+        }
+    };
+    let count = usize::try_from(count)
+        .map_err(|_| LexingError::RepeatError(format!("Cannot repeat {} times", count)))?;
+    let steps_left = lex.extras.limits.max_steps.saturating_sub(lex.extras.steps);
+    if count > steps_left {
+        return Err(LexingError::RepeatError(format!(
+            "Cannot repeat {} times, only {} steps are left",
+            count, steps_left
+        )));
+    }
+    lex.extras.stack.remove(0);
+
+    let (_, end_span) = block_bounds(lex)?;
+    let body = lex.span().end..lex.span().end + end_span.start;
+    for _ in 0..count {
+        take_step(&mut lex.extras, LexingError::RepeatError)?;
+        run_segment(lex, body.clone())?;
+        if interrupted(&lex.extras) {
+            break;
+        }
+    }
+    lex.bump(end_span.end);
+
+    Ok(())
+}
+
+fn op_jump(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
+    // the label is consumed even when the instruction is skipped
+    let label = label_argument(lex);
+
+    if label_is_set(&lex.extras.current_label) {
+        return Ok(());
+    }
+
+    lex.extras.current_label = Some(label?);
+
+    Ok(())
+}
+
+// label names are consumed by the instructions that take them, they can't appear alone
+fn check_label_name(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
+    Err(LexingError::InvalidOpcode(String::from(lex.slice())))
+}
+
+fn op_jumpi(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
+    let label = label_argument(lex);
+
+    if label_is_set(&lex.extras.current_label) {
+        return Ok(());
+    }
+
+    if lex.extras.stack.is_empty() {
+        return Err(LexingError::JumpiError(String::from("Stack must be at least 1 element deep")));
+    }
+
+    if lex.extras.stack[0].get_type() != Type::Bool {
+        return Err(LexingError::JumpiError(String::from("Top element must be a boolean value")));
+    }
+
+    let label = label?;
+    if lex.extras.stack[0].value == StackElValue::Bool(true) {
+        lex.extras.current_label = Some(label);
+    }
+    // removes the boolean value on the stack
+    lex.extras.stack = lex.extras.stack[1..].to_vec();
+
+    Ok(())
+}
+
+// jumps computed at runtime can't be checked beforehand,
+// the label must be defined further in the running code since jumps only go forward
+fn jump_to(
+    lex: &mut Lexer<Token>,
+    label: String,
+    span: Range<usize>,
+    error: fn(String) -> LexingError,
+) -> Result<(), LexingError> {
+    // the positions of a segment are relative to the segment, e.g. the body of a loop
+    let offset = lex.extras.offset;
+    let position = offset + lex.span().end;
+    let defined = lex
+        .extras
+        .code
+        .labels
+        .iter()
+        .any(|(name, start)| *name == label && *start >= position);
+    if !defined {
+        return Err(error(format!(
+            "Unknown label `{}` at {}..{}",
+            label,
+            offset + span.start,
+            offset + span.end
+        )));
+    }
+    lex.extras.current_label = Some(label);
+
+    Ok(())
+}
+
+// jumps to the label named by the string on top of the stack
+fn op_jump_dyn(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
+    check_blocks(lex)?;
+
+    if label_is_set(&lex.extras.current_label) {
+        return Ok(());
+    }
+
+    let label = match lex.extras.stack.first().map(|el| &el.value) {
+        None => return Err(LexingError::JumpDynError(String::from("Stack must be at least 1 element deep"))),
+        Some(StackElValue::String(label)) => label.clone(),
+        Some(val) => {
+            return Err(LexingError::JumpDynError(format!(
+                "Cannot jump to element of type {}",
+                val.type_name()
+            )))
+        }
+    };
+    lex.extras.stack.remove(0);
+
+    jump_to(lex, label, lex.span(), LexingError::JumpDynError)
+}
+
+// a case of a switch table: the value, the label and its position
+type SwitchCase = (StackElValue, String, Range<usize>);
+
+// parses the cases of a switch table, `src` starts right after the opening bracket
+// returns the cases and the length of the table including the closing bracket
+fn parse_switch_table(
+    src: &str,
+    stack: Option<&Stack>,
+) -> Result<(Vec<SwitchCase>, usize), LexingError> {
+    let invalid = |pos: usize| match src[pos..].split_whitespace().next() {
+        None => LexingError::SwitchError(String::from("Unterminated switch table")),
+        Some(rest) => LexingError::SwitchError(format!("Unexpected `{}` in switch table", rest)),
+    };
+    let skip_whitespace = |pos: usize| pos + src[pos..].len() - src[pos..].trim_start().len();
+
+    let mut cases = vec![];
+    let mut pos = skip_whitespace(0);
+    loop {
+        if src[pos..].starts_with(']') {
+            return Ok((cases, pos + 1));
+        }
+
+        let (value, len) = parse_literal_element(&src[pos..], stack, 0)?.ok_or_else(|| invalid(pos))?;
+        pos = skip_whitespace(pos + len);
+        if !src[pos..].starts_with("=>") {
+            return Err(invalid(pos));
+        }
+        pos = skip_whitespace(pos + 2);
+        let label_len = src[pos..]
+            .find(|c: char| !(c.is_ascii_lowercase() || c == '_'))
+            .unwrap_or(src.len() - pos);
+        if label_len == 0 {
+            return Err(invalid(pos));
+        }
+        cases.push((value, src[pos..pos + label_len].to_string(), pos..pos + label_len));
+
+        pos = skip_whitespace(pos + label_len);
+        if src[pos..].starts_with(',') {
+            pos = skip_whitespace(pos + 1);
+        } else if !src[pos..].starts_with(']') {
+            return Err(invalid(pos));
+        }
+    }
+}
+
+// consumes the switch table following the current token, the positions of the labels are made absolute
+fn switch_table(lex: &mut Lexer<Token>) -> Result<Vec<SwitchCase>, LexingError> {
+    let remainder = lex.remainder();
+    let trimmed = remainder.trim_start();
+    let table = match trimmed.strip_prefix('[') {
+        None => {
+            return Err(LexingError::SwitchError(String::from(
+                "SWITCH must be followed by a table of cases, e.g. `[1 => one, 2 => two]`",
+            )))
+        }
+        Some(table) => table,
+    };
+    let (cases, len) = parse_switch_table(table, literal_stack(&lex.extras))?;
+    let start = remainder.len() - trimmed.len() + 1;
+    let offset = lex.span().end + start;
+    lex.bump(start + len);
+
+    Ok(cases
+        .into_iter()
+        .map(|(value, label, span)| (value, label, span.start + offset..span.end + offset))
+        .collect())
+}
+
+// jumps to the label of the first case matching the value on top of the stack,
+// to the default label following the table otherwise, e.g. `SWITCH [1 => one, 2 => two] other`
+fn op_switch(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
+    // the table and the default label are consumed even when the instruction is skipped
+    let cases = switch_table(lex)?;
+    let default = label_argument(lex);
+    let default_end = lex.span().end;
+    check_blocks(lex)?;
+
+    if label_is_set(&lex.extras.current_label) {
+        return Ok(());
+    }
+
+    let default = default?;
+    let value = match lex.extras.stack.first() {
+        None => return Err(LexingError::SwitchError(String::from("Stack must be at least 1 element deep"))),
+        Some(el) => el.value.clone(),
+    };
+    let mut target = (default_end - default.len()..default_end, default);
+    for (case, label, span) in cases {
+        if case.get_type().unify(&value.get_type()).is_none() {
+            return Err(LexingError::SwitchError(format!(
+                "Cannot match element of type {} against case of type {}",
+                value.type_name(),
+                case.type_name()
+            )));
+        }
+        if same_value(&case, &value) {
+            target = (span, label);
+            break;
+        }
+    }
+    lex.extras.stack.remove(0);
+
+    let (span, label) = target;
+    jump_to(lex, label, span, LexingError::SwitchError)
+}
+
+// the type of the elements can be given after the opcode, e.g. `EMPTY_VECTOR int`
+fn op_empty_vector(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
+    // the annotation is consumed even when the instruction is skipped
+    let vector = match type_annotation(lex) {
+        None => LifoVector::new(),
+        Some(el_type) => LifoVector::of(el_type),
+    };
+
+    if label_is_set(&lex.extras.current_label) {
+        return Ok(());
+    }
+
+    let new_value = StackEl::new(StackElValue::Vector(vector));
+    lex.extras.stack = [vec![new_value], lex.extras.stack.clone()].concat();
+
+    Ok(())
+}
+
+fn op_insert_vector(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
+    if label_is_set(&lex.extras.current_label) {
+        return Ok(());
+    }
+
+    match insert_vector(&mut lex.extras.stack) {
+        Ok(new_stack) => {
+            lex.extras.stack = new_stack;
+        
+            Ok(())
+        }
+        Err(err) => Err(LexingError::InsertError(err))
+    }
+
+}
+
+fn int_argument(lex: &mut Lexer<Token>) -> Result<usize, LexingError> {
+    match lex.next() {
+        // the argument is an index or a length, it can't be negative
+        Some(Ok(Token::Int)) => lex
+            .slice()
+            .parse()
+            .map_err(|_| LexingError::InvalidInteger(String::from(lex.slice()))),
+        _ => Err(LexingError::InvalidInteger(String::from(lex.slice()))),
+    }
+}
+
+fn op_set(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
+    if label_is_set(&lex.extras.current_label) {
+        return Ok(());
+    }
+
+    let index = int_argument(lex)?;
+    match set(&lex.extras.stack, index) {
+        Ok(new_stack) => {
+            lex.extras.stack = new_stack;
+            Ok(())
+        }
+        Err(err) => Err(LexingError::SetError(err)),
+    }
+}
+
+fn op_remove(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
+    if label_is_set(&lex.extras.current_label) {
+        return Ok(());
+    }
+
+    let index = int_argument(lex)?;
+    match remove(&lex.extras.stack, index) {
+        Ok(new_stack) => {
+            lex.extras.stack = new_stack;
+            Ok(())
+        }
+        Err(err) => Err(LexingError::RemoveError(err)),
+    }
+}
+
+fn op_insert_at(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
+    if label_is_set(&lex.extras.current_label) {
+        return Ok(());
+    }
+
+    let index = int_argument(lex)?;
+    match insert_at(&lex.extras.stack, index) {
+        Ok(new_stack) => {
+            lex.extras.stack = new_stack;
+            Ok(())
+        }
+        Err(err) => Err(LexingError::InsertAtError(err)),
+    }
+}
+
+fn op_pop_back(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
+    if label_is_set(&lex.extras.current_label) {
+        return Ok(());
+    }
+
+    match pop_back(&lex.extras.stack) {
+        Ok(new_stack) => {
+            lex.extras.stack = new_stack;
+            Ok(())
+        }
+        Err(err) => Err(LexingError::PopBackError(err)),
+    }
+}
+
+fn op_sort(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
+    if label_is_set(&lex.extras.current_label) {
+        return Ok(());
+    }
+
+    match sort(&lex.extras.stack) {
+        Ok(new_stack) => {
+            lex.extras.stack = new_stack;
+            Ok(())
+        }
+        Err(err) => Err(LexingError::SortError(err)),
+    }
+}
+
+fn op_reverse(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
+    if label_is_set(&lex.extras.current_label) {
+        return Ok(());
+    }
+
+    match reverse(&lex.extras.stack) {
+        Ok(new_stack) => {
+            lex.extras.stack = new_stack;
+            Ok(())
+        }
+        Err(err) => Err(LexingError::ReverseError(err)),
+    }
+}
+
+fn op_contains(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
+    if label_is_set(&lex.extras.current_label) {
+        return Ok(());
+    }
+
+    match contains(&lex.extras.stack) {
+        Ok(new_stack) => {
+            lex.extras.stack = new_stack;
+            Ok(())
+        }
+        Err(err) => Err(LexingError::ContainsError(err)),
+    }
+}
+
+fn op_index_of(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
+    if label_is_set(&lex.extras.current_label) {
+        return Ok(());
+    }
+
+    match index_of(&lex.extras.stack) {
+        Ok(new_stack) => {
+            lex.extras.stack = new_stack;
+            Ok(())
+        }
+        Err(err) => Err(LexingError::IndexOfError(err)),
+    }
+}
+
+// the key and value types can be given after the opcode, e.g. `EMPTY_MAP string int`
+fn op_empty_map(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
+    let map = match type_annotation(lex) {
+        None => LifoMap::new(),
+        Some(key_type) => match type_annotation(lex) {
+            None => return Err(LexingError::InvalidToken(String::from(lex.slice()))),
+            Some(value_type) => LifoMap::of(key_type, value_type),
+        },
+    };
+    // keys are kept sorted, and values of different types can't be ordered
+    if map.key_type.contains_any() {
+        return Err(LexingError::EmptyMapError(String::from(
+            "Map keys can't contain values of type any, they must be ordered",
+        )));
+    }
+
+    if label_is_set(&lex.extras.current_label) {
+        return Ok(());
+    }
+
+    lex.extras
+        .stack
+        .insert(0, StackEl::new(StackElValue::Map(map)));
+
+    Ok(())
+}
+
+fn op_put(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
+    if label_is_set(&lex.extras.current_label) {
+        return Ok(());
+    }
+
+    match put(&lex.extras.stack) {
+        Ok(new_stack) => {
+            lex.extras.stack = new_stack;
+            Ok(())
+        }
+        Err(err) => Err(LexingError::PutError(err)),
+    }
+}
+
+fn op_get(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
+    if label_is_set(&lex.extras.current_label) {
+        return Ok(());
+    }
+
+    match get(&lex.extras.stack) {
+        Ok(new_stack) => {
+            lex.extras.stack = new_stack;
+            Ok(())
+        }
+        Err(err) => Err(LexingError::GetError(err)),
+    }
+}
+
+fn op_del(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
+    if label_is_set(&lex.extras.current_label) {
+        return Ok(());
+    }
+
+    match del(&lex.extras.stack) {
+        Ok(new_stack) => {
+            lex.extras.stack = new_stack;
+            Ok(())
+        }
+        Err(err) => Err(LexingError::DelError(err)),
+    }
+}
+
+fn op_has(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
+    if label_is_set(&lex.extras.current_label) {
+        return Ok(());
+    }
+
+    match has(&lex.extras.stack) {
+        Ok(new_stack) => {
+            lex.extras.stack = new_stack;
+            Ok(())
+        }
+        Err(err) => Err(LexingError::HasError(err)),
+    }
+}
+
+fn op_keys(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
+    if label_is_set(&lex.extras.current_label) {
+        return Ok(());
+    }
+
+    match keys(&lex.extras.stack) {
+        Ok(new_stack) => {
+            lex.extras.stack = new_stack;
+            Ok(())
+        }
+        Err(err) => Err(LexingError::KeysError(err)),
+    }
+}
+
+fn op_values(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
+    if label_is_set(&lex.extras.current_label) {
+        return Ok(());
+    }
+
+    match values(&lex.extras.stack) {
+        Ok(new_stack) => {
+            lex.extras.stack = new_stack;
+            Ok(())
+        }
+        Err(err) => Err(LexingError::ValuesError(err)),
+    }
+}
+
+fn op_empty_set(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
+    let set = match type_annotation(lex) {
+        None => LifoSet::new(),
+        Some(element_type) => LifoSet::of(element_type),
+    };
+    // elements are kept sorted, and values of different types can't be ordered
+    if set.element_type.contains_any() {
+        return Err(LexingError::EmptySetError(String::from(
+            "Set elements can't contain values of type any, they must be ordered",
+        )));
+    }
+
+    if label_is_set(&lex.extras.current_label) {
+        return Ok(());
+    }
+
+    lex.extras
+        .stack
+        .insert(0, StackEl::new(StackElValue::Set(set)));
+
+    Ok(())
+}
+
+fn op_union(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
+    if label_is_set(&lex.extras.current_label) {
+        return Ok(());
+    }
+
+    match union(&lex.extras.stack) {
+        Ok(new_stack) => {
+            lex.extras.stack = new_stack;
+            Ok(())
+        }
+        Err(err) => Err(LexingError::UnionError(err)),
+    }
+}
+
+fn op_intersect(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
+    if label_is_set(&lex.extras.current_label) {
+        return Ok(());
+    }
+
+    match intersect(&lex.extras.stack) {
+        Ok(new_stack) => {
+            lex.extras.stack = new_stack;
+            Ok(())
+        }
+        Err(err) => Err(LexingError::IntersectError(err)),
+    }
+}
+
+fn op_diff(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
+    if label_is_set(&lex.extras.current_label) {
+        return Ok(());
+    }
+
+    match diff(&lex.extras.stack) {
+        Ok(new_stack) => {
+            lex.extras.stack = new_stack;
+            Ok(())
+        }
+        Err(err) => Err(LexingError::DiffError(err)),
+    }
+}
+
+fn op_to_set(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
+    if label_is_set(&lex.extras.current_label) {
+        return Ok(());
+    }
+
+    match to_set(&lex.extras.stack) {
+        Ok(new_stack) => {
+            lex.extras.stack = new_stack;
+            Ok(())
+        }
+        Err(err) => Err(LexingError::ToSetError(err)),
+    }
+}
+
+fn op_to_vector(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
+    if label_is_set(&lex.extras.current_label) {
+        return Ok(());
+    }
+
+    match to_vector(&lex.extras.stack) {
+        Ok(new_stack) => {
+            lex.extras.stack = new_stack;
+            Ok(())
+        }
+        Err(err) => Err(LexingError::ToVectorError(err)),
+    }
+}
+
+fn op_pair(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
+    if label_is_set(&lex.extras.current_label) {
+        return Ok(());
+    }
+
+    match pair(&lex.extras.stack) {
+        Ok(new_stack) => {
+            lex.extras.stack = new_stack;
+            Ok(())
+        }
+        Err(err) => Err(LexingError::PairError(err)),
+    }
+}
+
+fn op_unpair(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
+    if label_is_set(&lex.extras.current_label) {
+        return Ok(());
+    }
+
+    match unpair(&lex.extras.stack) {
+        Ok(new_stack) => {
+            lex.extras.stack = new_stack;
+            Ok(())
+        }
+        Err(err) => Err(LexingError::UnpairError(err)),
+    }
+}
+
+fn op_car(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
+    if label_is_set(&lex.extras.current_label) {
+        return Ok(());
+    }
+
+    match car(&lex.extras.stack) {
+        Ok(new_stack) => {
+            lex.extras.stack = new_stack;
+            Ok(())
+        }
+        Err(err) => Err(LexingError::CarError(err)),
+    }
+}
+
+fn op_cdr(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
+    if label_is_set(&lex.extras.current_label) {
+        return Ok(());
+    }
+
+    match cdr(&lex.extras.stack) {
+        Ok(new_stack) => {
+            lex.extras.stack = new_stack;
+            Ok(())
+        }
+        Err(err) => Err(LexingError::CdrError(err)),
+    }
+}
+
+// consumes the field name following the current token, if any
+fn field_argument(lex: &mut Lexer<Token>) -> Option<String> {
+    let remainder = lex.remainder();
+    let trimmed = remainder.trim_start();
+    let len = field_name_len(trimmed)?;
+    if trimmed[len..].starts_with(':') {
+        return None;
+    }
+    let name = trimmed[..len].to_string();
+    lex.bump(remainder.len() - trimmed.len() + len);
+
+    Some(name)
+}
+
+fn op_get_field(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
+    let name = field_argument(lex);
+
+    if label_is_set(&lex.extras.current_label) {
+        return Ok(());
+    }
+
+    let result = match name {
+        None => Err(String::from("GET_FIELD must be followed by a field name")),
+        Some(name) => get_field(&lex.extras.stack, &name),
+    };
+    match result {
+        Ok(new_stack) => {
+            lex.extras.stack = new_stack;
+            Ok(())
+        }
+        Err(err) => Err(LexingError::GetFieldError(err)),
+    }
+}
+
+fn op_set_field(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
+    let name = field_argument(lex);
+
+    if label_is_set(&lex.extras.current_label) {
+        return Ok(());
+    }
+
+    let result = match name {
+        None => Err(String::from("SET_FIELD must be followed by a field name")),
+        Some(name) => set_field(&lex.extras.stack, &name),
+    };
+    match result {
+        Ok(new_stack) => {
+            lex.extras.stack = new_stack;
+            Ok(())
+        }
+        Err(err) => Err(LexingError::SetFieldError(err)),
+    }
+}
+
+fn op_index_opt(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
+    // like `INDEX`, the index comes from the stack when no literal follows
+    let literal = signed_int_argument(lex);
+
+    if label_is_set(&lex.extras.current_label) {
+        return Ok(());
+    }
+
+    let result = match literal {
+        Some(val) => safe_index(&lex.extras.stack, val),
+        None => dynamic_safe_index(&lex.extras.stack),
+    };
+    match result {
+        Ok(new_stack) => {
+            lex.extras.stack = new_stack;
+            Ok(())
+        }
+        Err(err) => Err(LexingError::IndexOptError(err)),
+    }
+}
+
+fn op_some(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
+    if label_is_set(&lex.extras.current_label) {
+        return Ok(());
+    }
+
+    match some(&lex.extras.stack) {
+        Ok(new_stack) => {
+            lex.extras.stack = new_stack;
+            Ok(())
+        }
+        Err(err) => Err(LexingError::SomeError(err)),
     }
 }
 
-fn check_label_name(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
-    match lex.extras.prev_op {
-        Token::Jump | Token::Jumpi => {
-            lex.extras.prev_op = Token::Invalid;
+// the type of the missing value can be given after the opcode, e.g. `NONE int`
+fn op_none(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
+    let el_type = type_annotation(lex).unwrap_or(Type::Unknown);
+
+    if label_is_set(&lex.extras.current_label) {
+        return Ok(());
+    }
+
+    lex.extras
+        .stack
+        .insert(0, StackEl::new(StackElValue::Option(el_type, None)));
+
+    Ok(())
+}
+
+fn op_unwrap(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
+    if label_is_set(&lex.extras.current_label) {
+        return Ok(());
+    }
+
+    match unwrap(&lex.extras.stack) {
+        Ok(new_stack) => {
+            lex.extras.stack = new_stack;
             Ok(())
         }
-        _ => Err(LexingError::InvalidOpcode(String::from(lex.slice()))),
+        Err(err) => Err(LexingError::UnwrapError(err)),
     }
 }
 
-fn op_jumpi(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
-    if lex.extras.stack.len() < 1 {
-        return Err(LexingError::JumpiError(String::from("Stack must be at least 1 element deep")));
+// consumes the name of a label or a function following the current token,
+// the invalid word is returned otherwise
+fn name_argument(lex: &mut Lexer<Token>) -> Result<String, String> {
+    let remainder = lex.remainder();
+    let trimmed = remainder.trim_start();
+    let len = trimmed
+        .find(|c: char| !(c.is_ascii_lowercase() || c == '_'))
+        .unwrap_or(trimmed.len());
+    if len == 0 || trimmed[len..].starts_with(|c: char| c.is_alphanumeric() || c == ':') {
+        let word = trimmed.split_whitespace().next().unwrap_or_default();
+        return Err(String::from(word));
     }
+    let name = trimmed[..len].to_string();
+    lex.bump(remainder.len() - trimmed.len() + len);
 
-    if lex.extras.stack[0].token != Token::Bool {
-        return Err(LexingError::JumpiError(String::from("Top element must be a boolean value")));
-    }
+    Ok(name)
+}
+
+fn label_argument(lex: &mut Lexer<Token>) -> Result<String, LexingError> {
+    name_argument(lex).map_err(LexingError::InvalidLabel)
+}
+
+// jumps to the label if the option on top of the stack is none, unwraps it otherwise
+fn op_if_none(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
+    let label = label_argument(lex);
 
     if label_is_set(&lex.extras.current_label) {
         return Ok(());
     }
-    
-    lex.extras.prev_op = Token::Jumpi;
 
-    match lex.next() {
-        Some(Ok(Token::LabelName)) => {
-            if lex.extras.stack[0].value == StackElValue::Bool(true) {
-                let label = lex.slice();
-                lex.extras.current_label = Some(label.to_string());
+    let label = label?;
+    match if_none(&lex.extras.stack) {
+        Ok((new_stack, is_none)) => {
+            lex.extras.stack = new_stack;
+            if is_none {
+                lex.extras.current_label = Some(label);
             }
-            // removes the boolean value on the stack
-            lex.extras.stack = lex.extras.stack[1..].to_vec();
+            Ok(())
+        }
+        Err(err) => Err(LexingError::IfNoneError(err)),
+    }
+}
+
+// the type of the other side must follow the opcode, e.g. `LEFT string` or `RIGHT int`
+fn op_or(lex: &mut Lexer<Token>, is_left: bool) -> Result<(), LexingError> {
+    let other_type = type_annotation(lex);
+
+    if label_is_set(&lex.extras.current_label) {
+        return Ok(());
+    }
 
+    let result = match (other_type, is_left) {
+        (None, true) => Err(String::from("LEFT must be followed by the type of the right value")),
+        (None, false) => Err(String::from("RIGHT must be followed by the type of the left value")),
+        (Some(right_type), true) => left(&lex.extras.stack, right_type),
+        (Some(left_type), false) => right(&lex.extras.stack, left_type),
+    };
+    match result {
+        Ok(new_stack) => {
+            lex.extras.stack = new_stack;
             Ok(())
         }
-        _ => Err(LexingError::InvalidLabel(String::from(lex.slice()))),
+        Err(err) if is_left => Err(LexingError::LeftError(err)),
+        Err(err) => Err(LexingError::RightError(err)),
     }
 }
 
-fn op_empty_vector(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
+// jumps to the label if the or value on top of the stack is a left value, unwraps it in both cases
+fn op_if_left(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
+    let label = label_argument(lex);
+
     if label_is_set(&lex.extras.current_label) {
         return Ok(());
     }
-    
-    let new_value = 
-        StackEl::new(
-            Token::EmptyVector, 
-            StackElValue::Vector(LifoVector::new())
-        );
-    lex.extras.stack = vec![vec![new_value], lex.extras.stack.clone()].concat();
 
-    Ok(())
+    let label = label?;
+    match if_left(&lex.extras.stack) {
+        Ok((new_stack, is_left)) => {
+            lex.extras.stack = new_stack;
+            if is_left {
+                lex.extras.current_label = Some(label);
+            }
+            Ok(())
+        }
+        Err(err) => Err(LexingError::IfLeftError(err)),
+    }
 }
 
-fn op_insert_vector(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
+fn op_compare(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
     if label_is_set(&lex.extras.current_label) {
         return Ok(());
     }
 
-    match insert_vector(&mut lex.extras.stack) {
+    match compare(&lex.extras.stack) {
         Ok(new_stack) => {
             lex.extras.stack = new_stack;
-        
             Ok(())
         }
-        Err(err) => Err(LexingError::InsertError(err))
+        Err(err) => Err(LexingError::CompareError(err)),
+    }
+}
+
+fn op_type_of(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
+    if label_is_set(&lex.extras.current_label) {
+        return Ok(());
     }
 
+    match type_of(&lex.extras.stack) {
+        Ok(new_stack) => {
+            lex.extras.stack = new_stack;
+            Ok(())
+        }
+        Err(err) => Err(LexingError::TypeOfError(err)),
+    }
 }
 
 fn op_size(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
@@ -464,7 +2700,7 @@ fn op_size(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
         return Ok(());
     }
     
-    match size(&mut lex.extras.stack) {
+    match size(&lex.extras.stack) {
         Ok(new_stack) => {
             lex.extras.stack = new_stack;
             Ok(())
@@ -473,28 +2709,274 @@ fn op_size(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
     }
 }
 
+// consumes the integer literal following the current token, if any, negative values are allowed
+fn signed_int_argument(lex: &mut Lexer<Token>) -> Option<isize> {
+    let remainder = lex.remainder();
+    let trimmed = remainder.trim_start();
+    let len = trimmed
+        .char_indices()
+        .find(|(i, c)| !(c.is_ascii_digit() || (*i == 0 && *c == '-')))
+        .map_or(trimmed.len(), |(i, _)| i);
+    let is_literal = !trimmed[len..].starts_with(|c: char| c.is_alphanumeric() || c == '_' || c == ':');
+    let val = trimmed[..len].parse().ok().filter(|_| is_literal)?;
+    lex.bump(remainder.len() - trimmed.len() + len);
+
+    Some(val)
+}
+
 fn op_index(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
+    // without a literal, `INDEX` takes the index from the stack
+    let literal = signed_int_argument(lex);
+
     if label_is_set(&lex.extras.current_label) {
         return Ok(());
     }
 
-    match lex.next() {
-        Some(Ok(Token::Int)) => {
-            let val = lex.slice().parse().unwrap();
-            match index(&mut lex.extras.stack, val) {
-                Ok(new_stack) => {
-                    lex.extras.stack = new_stack;
-                    Ok(())
-                },
-                Err(err) => Err(LexingError::IndexError(err))
-            }
+    let result = match literal {
+        Some(val) => index(&lex.extras.stack, val),
+        None => dynamic_index(&lex.extras.stack),
+    };
+    match result {
+        Ok(new_stack) => {
+            lex.extras.stack = new_stack;
+            Ok(())
         }
-        _ => Err(LexingError::InvalidInteger(String::from(lex.slice()))),
+        Err(err) => Err(LexingError::IndexError(err)),
+    }
+}
+
+fn op_match(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
+    if label_is_set(&lex.extras.current_label) {
+        return Ok(());
+    }
+
+    match regex_match(&lex.extras.stack) {
+        Ok(new_stack) => {
+            lex.extras.stack = new_stack;
+            Ok(())
+        }
+        Err(err) => Err(LexingError::MatchError(err)),
+    }
+}
+
+fn op_capture(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
+    if label_is_set(&lex.extras.current_label) {
+        return Ok(());
+    }
+
+    match capture(&lex.extras.stack) {
+        Ok(new_stack) => {
+            lex.extras.stack = new_stack;
+            Ok(())
+        }
+        Err(err) => Err(LexingError::CaptureError(err)),
+    }
+}
+
+fn op_regex_replace(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
+    if label_is_set(&lex.extras.current_label) {
+        return Ok(());
+    }
+
+    match regex_replace(&lex.extras.stack) {
+        Ok(new_stack) => {
+            lex.extras.stack = new_stack;
+            Ok(())
+        }
+        Err(err) => Err(LexingError::RegexReplaceError(err)),
+    }
+}
+
+fn op_to_str(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
+    if label_is_set(&lex.extras.current_label) {
+        return Ok(());
+    }
+
+    match to_str(&lex.extras.stack) {
+        Ok(new_stack) => {
+            lex.extras.stack = new_stack;
+            Ok(())
+        }
+        Err(err) => Err(LexingError::ToStrError(err)),
+    }
+}
+
+fn op_parse_int(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
+    if label_is_set(&lex.extras.current_label) {
+        return Ok(());
+    }
+
+    match parse_int(&lex.extras.stack) {
+        Ok(new_stack) => {
+            lex.extras.stack = new_stack;
+            Ok(())
+        }
+        Err(err) => Err(LexingError::ParseIntError(err)),
+    }
+}
+
+fn op_parse_bool(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
+    if label_is_set(&lex.extras.current_label) {
+        return Ok(());
+    }
+
+    match parse_bool(&lex.extras.stack) {
+        Ok(new_stack) => {
+            lex.extras.stack = new_stack;
+            Ok(())
+        }
+        Err(err) => Err(LexingError::ParseBoolError(err)),
+    }
+}
+
+fn op_explode(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
+    if label_is_set(&lex.extras.current_label) {
+        return Ok(());
+    }
+
+    match explode(&lex.extras.stack) {
+        Ok(new_stack) => {
+            lex.extras.stack = new_stack;
+            Ok(())
+        }
+        Err(err) => Err(LexingError::ExplodeError(err)),
+    }
+}
+
+fn op_implode(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
+    if label_is_set(&lex.extras.current_label) {
+        return Ok(());
+    }
+
+    match implode(&lex.extras.stack) {
+        Ok(new_stack) => {
+            lex.extras.stack = new_stack;
+            Ok(())
+        }
+        Err(err) => Err(LexingError::ImplodeError(err)),
+    }
+}
+
+fn op_is_digit(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
+    if label_is_set(&lex.extras.current_label) {
+        return Ok(());
+    }
+
+    match is_digit(&lex.extras.stack) {
+        Ok(new_stack) => {
+            lex.extras.stack = new_stack;
+            Ok(())
+        }
+        Err(err) => Err(LexingError::IsDigitError(err)),
+    }
+}
+
+fn op_is_alpha(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
+    if label_is_set(&lex.extras.current_label) {
+        return Ok(());
+    }
+
+    match is_alpha(&lex.extras.stack) {
+        Ok(new_stack) => {
+            lex.extras.stack = new_stack;
+            Ok(())
+        }
+        Err(err) => Err(LexingError::IsAlphaError(err)),
+    }
+}
+
+fn op_to_upper(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
+    if label_is_set(&lex.extras.current_label) {
+        return Ok(());
+    }
+
+    match to_upper(&lex.extras.stack) {
+        Ok(new_stack) => {
+            lex.extras.stack = new_stack;
+            Ok(())
+        }
+        Err(err) => Err(LexingError::ToUpperError(err)),
+    }
+}
+
+fn op_slice(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
+    if label_is_set(&lex.extras.current_label) {
+        return Ok(());
+    }
+
+    let start = int_argument(lex)?;
+    let end = int_argument(lex)?;
+    match slice(&lex.extras.stack, start, end) {
+        Ok(new_stack) => {
+            lex.extras.stack = new_stack;
+            Ok(())
+        }
+        Err(err) => Err(LexingError::SliceError(err)),
+    }
+}
+
+fn op_bytes_to_int(lex: &mut Lexer<Token>, big_endian: bool) -> Result<(), LexingError> {
+    if label_is_set(&lex.extras.current_label) {
+        return Ok(());
+    }
+
+    match bytes_to_int(&lex.extras.stack, big_endian) {
+        Ok(new_stack) => {
+            lex.extras.stack = new_stack;
+            Ok(())
+        }
+        Err(err) => Err(LexingError::BytesToIntError(err)),
+    }
+}
+
+fn op_int_to_bytes(lex: &mut Lexer<Token>, big_endian: bool) -> Result<(), LexingError> {
+    if label_is_set(&lex.extras.current_label) {
+        return Ok(());
+    }
+
+    match int_to_bytes(&lex.extras.stack, big_endian) {
+        Ok(new_stack) => {
+            lex.extras.stack = new_stack;
+            Ok(())
+        }
+        Err(err) => Err(LexingError::IntToBytesError(err)),
+    }
+}
+
+fn op_bytes_to_str(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
+    if label_is_set(&lex.extras.current_label) {
+        return Ok(());
+    }
+
+    match bytes_to_str(&lex.extras.stack) {
+        Ok(new_stack) => {
+            lex.extras.stack = new_stack;
+            Ok(())
+        }
+        Err(err) => Err(LexingError::BytesToStrError(err)),
+    }
+}
+
+fn op_str_to_bytes(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
+    if label_is_set(&lex.extras.current_label) {
+        return Ok(());
+    }
+
+    match str_to_bytes(&lex.extras.stack) {
+        Ok(new_stack) => {
+            lex.extras.stack = new_stack;
+            Ok(())
+        }
+        Err(err) => Err(LexingError::StrToBytesError(err)),
     }
 }
 
 fn op_log(lex: &mut Lexer<Token>) -> Result<(), LexingError> {
-    println!("current stack: {:?}", lex.extras.stack);
+    if label_is_set(&lex.extras.current_label) {
+        return Ok(());
+    }
+
+    println!("current stack: {}", render_stack(&lex.extras.stack));
 
     Ok(())
 }
\ No newline at end of file
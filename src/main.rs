@@ -1,13 +1,26 @@
 use logos::Logos;
+use std::io;
 mod lexer;
+mod regex;
 mod stack;
 
 fn main() {
-    use text_io::read;
-    println!("\nPlease input a valid LIFO program:");
-    let input: String = read!("{}\n");
-    let mut lex = lexer::Token::lexer(&input);
-    while let Some(token) = lex.next() {
+    println!("\nPlease input a valid LIFO program (end the input with Ctrl-D):");
+    // the whole input is read so that programs and strings can span several lines
+    let input = match io::read_to_string(io::stdin()) {
+        Ok(input) => input,
+        Err(err) => panic!("the input couldn't be read: {:?}", err),
+    };
+    // the program is checked before any of its instructions runs
+    let state = match lexer::State::checked(&input) {
+        Ok(state) => state,
+        Err(err) => panic!(
+            "an error occurred with this input: `{:?}`\nerror: {:?}",
+            input, err
+        ),
+    };
+    let mut lex = lexer::Token::lexer_with_extras(&input, state);
+    for token in lex.by_ref() {
         match token {
             Ok(_tk) => (), // println!("{:#?}", tk),
             Err(err) => panic!(
@@ -16,7 +29,7 @@ fn main() {
             ),
         }
     }
-    if lex.extras.stack.len() == 0 {
+    if lex.extras.stack.is_empty() {
         println!("\nEmpty stack")
     } else {
         println!(
@@ -29,7 +42,7 @@ fn main() {
 
     // let mut lex = lexer::Token::lexer(code);
 
-    // while let Some(token) = lex.next() {
+    // for token in lex.by_ref() {
     //     match token {
     //         Ok(_tk) => (), // println!("{:#?}", tk),
     //         Err(err) => panic!("an error occurred: {:?}", err),
@@ -43,23 +56,22 @@ fn main() {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::lexer::Token;
     use crate::stack::{StackEl, StackElValue};
     use lexer::LexingError;
-    use stack::LifoVector;
+    use stack::{LifoVector, Type};
 
     #[test]
     fn push() {
         let code = "PUSH 69";
         let mut lex = lexer::Token::lexer(code);
-        while let Some(token) = lex.next() {
+        for token in lex.by_ref() {
             match token {
                 Ok(_tk) => (), // println!("{:#?}", tk),
                 Err(_) => panic!("some error occurred"),
             }
         }
 
-        let expected_stack = vec![StackEl::new(Token::Int, StackElValue::Int(69))];
+        let expected_stack = vec![StackEl::new(StackElValue::Int(69))];
         assert_eq!(lex.extras.stack.len(), 1);
         assert_eq!(lex.extras.stack, expected_stack);
     }
@@ -68,7 +80,7 @@ mod tests {
     fn invalid_push() {
         let code = "PUSH test";
         let mut lex = lexer::Token::lexer(code);
-        while let Some(token) = lex.next() {
+        for token in lex.by_ref() {
             match token {
                 Ok(_tk) => panic!("invalid push shouldn't work"),
                 Err(err) => assert_eq!(err, LexingError::InvalidPush(String::from("test"))),
@@ -80,14 +92,14 @@ mod tests {
     fn push_int() {
         let code = "PUSH_INT 6";
         let mut lex = lexer::Token::lexer(code);
-        while let Some(token) = lex.next() {
+        for token in lex.by_ref() {
             match token {
                 Ok(_tk) => (), // println!("{:#?}", tk),
                 Err(_) => panic!("some error occurred"),
             }
         }
 
-        let expected_stack = vec![StackEl::new(Token::Int, StackElValue::Int(6))];
+        let expected_stack = vec![StackEl::new(StackElValue::Int(6))];
         assert_eq!(lex.extras.stack.len(), 1);
         assert_eq!(lex.extras.stack, expected_stack);
     }
@@ -96,7 +108,7 @@ mod tests {
     fn invalid_push_int() {
         let code = "PUSH_INT true";
         let mut lex = lexer::Token::lexer(code);
-        while let Some(token) = lex.next() {
+        for token in lex.by_ref() {
             match token {
                 Ok(_tk) => panic!("invalid push int shouldn't work"),
                 Err(err) => assert_eq!(err, LexingError::InvalidInteger(String::from("true"))),
@@ -108,14 +120,14 @@ mod tests {
     fn push_bool() {
         let code = "PUSH_BOOL false";
         let mut lex = lexer::Token::lexer(code);
-        while let Some(token) = lex.next() {
+        for token in lex.by_ref() {
             match token {
                 Ok(_tk) => (), // println!("{:#?}", tk),
                 Err(_) => panic!("some error occurred"),
             }
         }
 
-        let expected_stack = vec![StackEl::new(Token::Bool, StackElValue::Bool(false))];
+        let expected_stack = vec![StackEl::new(StackElValue::Bool(false))];
         assert_eq!(lex.extras.stack.len(), 1);
         assert_eq!(lex.extras.stack, expected_stack);
     }
@@ -124,17 +136,14 @@ mod tests {
     fn push_string() {
         let code = "PUSH_STR \"test\"";
         let mut lex = lexer::Token::lexer(code);
-        while let Some(token) = lex.next() {
+        for token in lex.by_ref() {
             match token {
                 Ok(_tk) => (), // println!("{:#?}", tk),
                 Err(_) => panic!("some error occurred"),
             }
         }
 
-        let expected_stack = vec![StackEl::new(
-            Token::String,
-            StackElValue::String(String::from("test")),
-        )];
+        let expected_stack = vec![StackEl::new(StackElValue::String(String::from("test")))];
         assert_eq!(lex.extras.stack.len(), 1);
         assert_eq!(lex.extras.stack, expected_stack);
     }
@@ -143,7 +152,7 @@ mod tests {
     fn invalid_push_bool() {
         let code = "PUSH_BOOL 69";
         let mut lex = lexer::Token::lexer(code);
-        while let Some(token) = lex.next() {
+        for token in lex.by_ref() {
             match token {
                 Ok(_tk) => panic!("invalid push bool shouldn't work"),
                 Err(err) => assert_eq!(err, LexingError::InvalidBool(String::from("69"))),
@@ -155,7 +164,7 @@ mod tests {
     fn dup() {
         let code = "PUSH 69 DUP";
         let mut lex = lexer::Token::lexer(code);
-        while let Some(token) = lex.next() {
+        for token in lex.by_ref() {
             match token {
                 Ok(_tk) => (), // println!("{:#?}", tk),
                 Err(_) => panic!("some error occurred"),
@@ -163,8 +172,8 @@ mod tests {
         }
 
         let expected_stack = vec![
-            StackEl::new(Token::Int, StackElValue::Int(69)),
-            StackEl::new(Token::Int, StackElValue::Int(69)),
+            StackEl::new(StackElValue::Int(69)),
+            StackEl::new(StackElValue::Int(69)),
         ];
         assert_eq!(lex.extras.stack.len(), 2);
         assert_eq!(lex.extras.stack, expected_stack);
@@ -174,7 +183,7 @@ mod tests {
     fn invalid_dup() {
         let code = "DUP";
         let mut lex = lexer::Token::lexer(code);
-        while let Some(token) = lex.next() {
+        for token in lex.by_ref() {
             match token {
                 Ok(_tk) => panic!("invalid dup shouldn't work"),
                 Err(err) => assert_eq!(
@@ -189,14 +198,14 @@ mod tests {
     fn add() {
         let code = "PUSH 69 PUSH 21 ADD";
         let mut lex = lexer::Token::lexer(code);
-        while let Some(token) = lex.next() {
+        for token in lex.by_ref() {
             match token {
                 Ok(_tk) => (), // println!("{:#?}", tk),
                 Err(_) => panic!("some error occurred"),
             }
         }
 
-        let expected_stack = vec![StackEl::new(Token::Int, StackElValue::Int(90))];
+        let expected_stack = vec![StackEl::new(StackElValue::Int(90))];
         assert_eq!(lex.extras.stack.len(), 1);
         assert_eq!(lex.extras.stack, expected_stack);
     }
@@ -205,7 +214,7 @@ mod tests {
     fn invalid_add() {
         let code = "PUSH 69 PUSH false ADD";
         let mut lex = lexer::Token::lexer(code);
-        while let Some(token) = lex.next() {
+        for token in lex.by_ref() {
             match token {
                 Ok(_tk) => (), // panic!("invalid add shouldn't work, stack: {:?}", lex.extras.stack),
                 Err(err) => assert_eq!(
@@ -220,23 +229,23 @@ mod tests {
     fn sub() {
         let code = "PUSH 21 PUSH 69 SUB";
         let mut lex = lexer::Token::lexer(code);
-        while let Some(token) = lex.next() {
+        for token in lex.by_ref() {
             match token {
                 Ok(_tk) => (), // println!("{:#?}", tk),
                 Err(_) => panic!("some error occurred"),
             }
         }
 
-        let expected_stack = vec![StackEl::new(Token::Int, StackElValue::Int(48))];
+        let expected_stack = vec![StackEl::new(StackElValue::Int(48))];
         assert_eq!(lex.extras.stack.len(), 1);
         assert_eq!(lex.extras.stack, expected_stack);
     }
 
     #[test]
     fn invalid_sub_1() {
-        let code = "PUSH 20 PUSH 25 SUB";
+        let code = "PUSH 1 PUSH -170141183460469231731687303715884105728 SUB";
         let mut lex = lexer::Token::lexer(code);
-        while let Some(token) = lex.next() {
+        for token in lex.by_ref() {
             match token {
                 Ok(_tk) => (),
                 Err(err) => assert_eq!(
@@ -251,7 +260,7 @@ mod tests {
     fn invalid_sub_2() {
         let code = "PUSH 20 PUSH true SUB";
         let mut lex = lexer::Token::lexer(code);
-        while let Some(token) = lex.next() {
+        for token in lex.by_ref() {
             match token {
                 Ok(_tk) => (),
                 Err(err) => assert_eq!(
@@ -266,14 +275,14 @@ mod tests {
     fn mul() {
         let code = "PUSH 20 PUSH 5 MUL";
         let mut lex = lexer::Token::lexer(code);
-        while let Some(token) = lex.next() {
+        for token in lex.by_ref() {
             match token {
                 Ok(_tk) => (), // println!("{:#?}", tk),
                 Err(_) => panic!("some error occurred"),
             }
         }
 
-        let expected_stack = vec![StackEl::new(Token::Int, StackElValue::Int(100))];
+        let expected_stack = vec![StackEl::new(StackElValue::Int(100))];
         assert_eq!(lex.extras.stack.len(), 1);
         assert_eq!(lex.extras.stack, expected_stack);
     }
@@ -282,7 +291,7 @@ mod tests {
     fn invalid_mul() {
         let code = "PUSH 20 PUSH true MUL";
         let mut lex = lexer::Token::lexer(code);
-        while let Some(token) = lex.next() {
+        for token in lex.by_ref() {
             match token {
                 Ok(_tk) => (),
                 Err(err) => assert_eq!(
@@ -297,14 +306,14 @@ mod tests {
     fn add_mul_sub() {
         let code = "PUSH 20 PUSH 5 ADD PUSH 4 MUL PUSH 150 SUB";
         let mut lex = lexer::Token::lexer(code);
-        while let Some(token) = lex.next() {
+        for token in lex.by_ref() {
             match token {
                 Ok(_tk) => (), // println!("{:#?}", tk),
                 Err(_) => panic!("some error occurred"),
             }
         }
 
-        let expected_stack = vec![StackEl::new(Token::Int, StackElValue::Int(50))];
+        let expected_stack = vec![StackEl::new(StackElValue::Int(50))];
         assert_eq!(lex.extras.stack.len(), 1);
         assert_eq!(lex.extras.stack, expected_stack);
     }
@@ -313,14 +322,14 @@ mod tests {
     fn eq() {
         let code = "PUSH 20 PUSH 5 EQ";
         let mut lex = lexer::Token::lexer(code);
-        while let Some(token) = lex.next() {
+        for token in lex.by_ref() {
             match token {
                 Ok(_tk) => (), // println!("{:#?}", tk),
                 Err(_) => panic!("some error occurred"),
             }
         }
 
-        let expected_stack = vec![StackEl::new(Token::Bool, StackElValue::Bool(false))];
+        let expected_stack = vec![StackEl::new(StackElValue::Bool(false))];
         assert_eq!(lex.extras.stack.len(), 1);
         assert_eq!(lex.extras.stack, expected_stack);
     }
@@ -329,7 +338,7 @@ mod tests {
     fn invalid_eq_1() {
         let code = "PUSH 20 PUSH true EQ";
         let mut lex = lexer::Token::lexer(code);
-        while let Some(token) = lex.next() {
+        for token in lex.by_ref() {
             match token {
                 Ok(_tk) => (),
                 Err(err) => assert_eq!(
@@ -344,7 +353,7 @@ mod tests {
     fn invalid_eq_2() {
         let code = "PUSH 20 EQ";
         let mut lex = lexer::Token::lexer(code);
-        while let Some(token) = lex.next() {
+        for token in lex.by_ref() {
             match token {
                 Ok(_tk) => (),
                 Err(err) => assert_eq!(
@@ -359,14 +368,14 @@ mod tests {
     fn neq() {
         let code = "PUSH 20 PUSH 5 NEQ";
         let mut lex = lexer::Token::lexer(code);
-        while let Some(token) = lex.next() {
+        for token in lex.by_ref() {
             match token {
                 Ok(_tk) => (), // println!("{:#?}", tk),
                 Err(_) => panic!("some error occurred"),
             }
         }
 
-        let expected_stack = vec![StackEl::new(Token::Bool, StackElValue::Bool(true))];
+        let expected_stack = vec![StackEl::new(StackElValue::Bool(true))];
         assert_eq!(lex.extras.stack.len(), 1);
         assert_eq!(lex.extras.stack, expected_stack);
     }
@@ -375,7 +384,7 @@ mod tests {
     fn invalid_neq_1() {
         let code = "PUSH 20 PUSH true NEQ";
         let mut lex = lexer::Token::lexer(code);
-        while let Some(token) = lex.next() {
+        for token in lex.by_ref() {
             match token {
                 Ok(_tk) => (),
                 Err(err) => assert_eq!(
@@ -390,7 +399,7 @@ mod tests {
     fn invalid_neq_2() {
         let code = "PUSH 20 NEQ";
         let mut lex = lexer::Token::lexer(code);
-        while let Some(token) = lex.next() {
+        for token in lex.by_ref() {
             match token {
                 Ok(_tk) => (),
                 Err(err) => assert_eq!(
@@ -405,14 +414,14 @@ mod tests {
     fn pop() {
         let code = "PUSH 20 PUSH 5 POP";
         let mut lex = lexer::Token::lexer(code);
-        while let Some(token) = lex.next() {
+        for token in lex.by_ref() {
             match token {
                 Ok(_tk) => (), // println!("{:#?}", tk),
                 Err(_) => panic!("some error occurred"),
             }
         }
 
-        let expected_stack = vec![StackEl::new(Token::Int, StackElValue::Int(20))];
+        let expected_stack = vec![StackEl::new(StackElValue::Int(20))];
         assert_eq!(lex.extras.stack.len(), 1);
         assert_eq!(lex.extras.stack, expected_stack);
     }
@@ -421,7 +430,7 @@ mod tests {
     fn invalid_pop() {
         let code = "POP";
         let mut lex = lexer::Token::lexer(code);
-        while let Some(token) = lex.next() {
+        for token in lex.by_ref() {
             match token {
                 Ok(_tk) => (),
                 Err(err) => assert_eq!(
@@ -436,7 +445,7 @@ mod tests {
     fn swap() {
         let code = "PUSH 20 PUSH 5 SWAP";
         let mut lex = lexer::Token::lexer(code);
-        while let Some(token) = lex.next() {
+        for token in lex.by_ref() {
             match token {
                 Ok(_tk) => (), // println!("{:#?}", tk),
                 Err(_) => panic!("some error occurred"),
@@ -444,8 +453,8 @@ mod tests {
         }
 
         let expected_stack = vec![
-            StackEl::new(Token::Int, StackElValue::Int(20)),
-            StackEl::new(Token::Int, StackElValue::Int(5)),
+            StackEl::new(StackElValue::Int(20)),
+            StackEl::new(StackElValue::Int(5)),
         ];
         assert_eq!(lex.extras.stack.len(), 2);
         assert_eq!(lex.extras.stack, expected_stack);
@@ -455,7 +464,7 @@ mod tests {
     fn invalid_swap() {
         let code = "PUSH 69 SWAP";
         let mut lex = lexer::Token::lexer(code);
-        while let Some(token) = lex.next() {
+        for token in lex.by_ref() {
             match token {
                 Ok(_tk) => (),
                 Err(err) => assert_eq!(
@@ -474,17 +483,16 @@ mod tests {
             CONCAT
         ";
         let mut lex = lexer::Token::lexer(code);
-        while let Some(token) = lex.next() {
+        for token in lex.by_ref() {
             match token {
                 Ok(_tk) => (), // println!("{:#?}", tk),
                 Err(_) => panic!("some error occurred"),
             }
         }
 
-        let expected_stack = vec![StackEl::new(
-            Token::String,
-            StackElValue::String(String::from("hello world")),
-        )];
+        let expected_stack = vec![StackEl::new(StackElValue::String(String::from(
+            "hello world",
+        )))];
         assert_eq!(lex.extras.stack.len(), 1);
         assert_eq!(lex.extras.stack, expected_stack);
     }
@@ -497,12 +505,14 @@ mod tests {
             CONCAT
         ";
         let mut lex = lexer::Token::lexer(code);
-        while let Some(token) = lex.next() {
+        for token in lex.by_ref() {
             match token {
                 Ok(_tk) => (),
                 Err(err) => assert_eq!(
                     err,
-                    LexingError::ConcatError(String::from("Only strings can be concatenated"))
+                    LexingError::ConcatError(String::from(
+                        "Only strings or bytes can be concatenated"
+                    ))
                 ),
             }
         }
@@ -518,7 +528,7 @@ mod tests {
                 PUSH 420
         ";
         let mut lex = lexer::Token::lexer(code);
-        while let Some(token) = lex.next() {
+        for token in lex.by_ref() {
             match token {
                 Ok(_tk) => (), // println!("{:#?}", tk),
                 Err(err) => panic!("an error occurred: {:?}", err),
@@ -526,11 +536,8 @@ mod tests {
         }
 
         let expected_stack = vec![
-            StackEl::new(Token::Int, StackElValue::Int(420)),
-            StackEl::new(
-                Token::String,
-                StackElValue::String(String::from("hello world")),
-            ),
+            StackEl::new(StackElValue::Int(420)),
+            StackEl::new(StackElValue::String(String::from("hello world"))),
         ];
         // println!("stack: {:?}", lex.extras.stack);
         assert_eq!(lex.extras.stack.len(), 2);
@@ -547,7 +554,7 @@ mod tests {
                 PUSH 420
         ";
         let mut lex = lexer::Token::lexer(code);
-        while let Some(token) = lex.next() {
+        for token in lex.by_ref() {
             match token {
                 Ok(_tk) => (),
                 Err(err) => {
@@ -568,7 +575,7 @@ mod tests {
                 PUSH 420
         ";
         let mut lex = lexer::Token::lexer(code);
-        while let Some(token) = lex.next() {
+        for token in lex.by_ref() {
             match token {
                 Ok(_tk) => (),
                 Err(err) => {
@@ -588,7 +595,7 @@ mod tests {
                 PUSH 420
         ";
         let mut lex = lexer::Token::lexer(code);
-        while let Some(token) = lex.next() {
+        for token in lex.by_ref() {
             match token {
                 Ok(_tk) => (),
                 Err(err) => {
@@ -610,7 +617,7 @@ mod tests {
                 PUSH 420
         ";
         let mut lex = lexer::Token::lexer(code);
-        while let Some(token) = lex.next() {
+        for token in lex.by_ref() {
             match token {
                 Ok(_tk) => (), // println!("{:#?}", tk),
                 Err(err) => panic!("an error occurred: {:?}", err),
@@ -618,11 +625,8 @@ mod tests {
         }
 
         let expected_stack = vec![
-            StackEl::new(Token::Int, StackElValue::Int(420)),
-            StackEl::new(
-                Token::String,
-                StackElValue::String(String::from("hello world")),
-            ),
+            StackEl::new(StackElValue::Int(420)),
+            StackEl::new(StackElValue::String(String::from("hello world"))),
         ];
         // println!("stack: {:?}", lex.extras.stack);
         assert_eq!(lex.extras.stack.len(), 2);
@@ -639,18 +643,18 @@ mod tests {
             INSERT
         ";
         let mut lex = lexer::Token::lexer(code);
-        while let Some(token) = lex.next() {
+        for token in lex.by_ref() {
             match token {
                 Ok(_tk) => (), // println!("{:#?}", tk),
                 Err(err) => panic!("an error occurred: {:?}", err),
             }
         }
 
-        let lifo_vector = LifoVector::VectorOfInt(vec![69, 420]);
-        let expected_stack = vec![StackEl::new(
-            Token::InsertVector,
-            StackElValue::Vector(lifo_vector),
-        )];
+        let lifo_vector = LifoVector::from_elements(
+            Type::Int,
+            vec![StackElValue::Int(69), StackElValue::Int(420)],
+        );
+        let expected_stack = vec![StackEl::new(StackElValue::Vector(lifo_vector))];
         // println!("stack: {:?}", lex.extras.stack);
         assert_eq!(lex.extras.stack.len(), 1);
         assert_eq!(lex.extras.stack, expected_stack);
@@ -666,7 +670,7 @@ mod tests {
             INSERT
         ";
         let mut lex = lexer::Token::lexer(code);
-        while let Some(token) = lex.next() {
+        for token in lex.by_ref() {
             match token {
                 Ok(_tk) => (),
                 Err(err) => {
@@ -689,7 +693,7 @@ mod tests {
             INSERT
         ";
         let mut lex = lexer::Token::lexer(code);
-        while let Some(token) = lex.next() {
+        for token in lex.by_ref() {
             match token {
                 Ok(_tk) => (),
                 Err(err) => {
@@ -713,7 +717,7 @@ mod tests {
             INSERT
         ";
         let mut lex = lexer::Token::lexer(code);
-        while let Some(token) = lex.next() {
+        for token in lex.by_ref() {
             match token {
                 Ok(_tk) => (),
                 Err(err) => {
@@ -740,14 +744,14 @@ mod tests {
             SIZE
         ";
         let mut lex = lexer::Token::lexer(code);
-        while let Some(token) = lex.next() {
+        for token in lex.by_ref() {
             match token {
                 Ok(_tk) => (), // println!("{:#?}", tk),
                 Err(err) => panic!("an error occurred: {:?}", err),
             }
         }
 
-        let expected_stack = vec![StackEl::new(Token::Size, StackElValue::Int(2))];
+        let expected_stack = vec![StackEl::new(StackElValue::Int(2))];
         // println!("stack: {:?}", lex.extras.stack);
         assert_eq!(lex.extras.stack.len(), 1);
         assert_eq!(lex.extras.stack, expected_stack);
@@ -760,19 +764,48 @@ mod tests {
             SIZE
         ";
         let mut lex = lexer::Token::lexer(code);
-        while let Some(token) = lex.next() {
+        for token in lex.by_ref() {
             match token {
                 Ok(_tk) => (), // println!("{:#?}", tk),
                 Err(err) => panic!("an error occurred: {:?}", err),
             }
         }
 
-        let expected_stack = vec![StackEl::new(Token::Size, StackElValue::Int(11))];
+        let expected_stack = vec![StackEl::new(StackElValue::Int(11))];
         // println!("stack: {:?}", lex.extras.stack);
         assert_eq!(lex.extras.stack.len(), 1);
         assert_eq!(lex.extras.stack, expected_stack);
     }
 
+    #[test]
+    fn multibyte_string_size() {
+        let code = r#"
+            PUSH "héllo"
+            SIZE
+            PUSH "héllo"
+            DUP
+            SIZE
+            PUSH 1
+            SWAP
+            SUB
+            INDEX
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![
+            StackEl::new(StackElValue::Char('o')),
+            StackEl::new(StackElValue::Int(5)),
+        ];
+        assert_eq!(lex.extras.stack.len(), 2);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
     #[test]
     fn invalid_size() {
         let code = "
@@ -780,7 +813,7 @@ mod tests {
             SIZE
         ";
         let mut lex = lexer::Token::lexer(code);
-        while let Some(token) = lex.next() {
+        for token in lex.by_ref() {
             match token {
                 Ok(_tk) => (),
                 Err(err) => {
@@ -807,14 +840,14 @@ mod tests {
             INDEX 1
         ";
         let mut lex = lexer::Token::lexer(code);
-        while let Some(token) = lex.next() {
+        for token in lex.by_ref() {
             match token {
                 Ok(_tk) => (), // println!("{:#?}", tk),
                 Err(err) => panic!("an error occurred: {:?}", err),
             }
         }
 
-        let expected_stack = vec![StackEl::new(Token::Index, StackElValue::Int(420))];
+        let expected_stack = vec![StackEl::new(StackElValue::Int(420))];
         // println!("stack: {:?}", lex.extras.stack);
         assert_eq!(lex.extras.stack.len(), 1);
         assert_eq!(lex.extras.stack, expected_stack);
@@ -827,17 +860,14 @@ mod tests {
             INDEX 6
         ";
         let mut lex = lexer::Token::lexer(code);
-        while let Some(token) = lex.next() {
+        for token in lex.by_ref() {
             match token {
                 Ok(_tk) => (), // println!("{:#?}", tk),
                 Err(err) => panic!("an error occurred: {:?}", err),
             }
         }
 
-        let expected_stack = vec![StackEl::new(
-            Token::Index,
-            StackElValue::String(String::from("w")),
-        )];
+        let expected_stack = vec![StackEl::new(StackElValue::Char('w'))];
         // println!("stack: {:?}", lex.extras.stack);
         assert_eq!(lex.extras.stack.len(), 1);
         assert_eq!(lex.extras.stack, expected_stack);
@@ -850,7 +880,7 @@ mod tests {
             PUSH "hello world"
         "#;
         let mut lex = lexer::Token::lexer(code);
-        while let Some(token) = lex.next() {
+        for token in lex.by_ref() {
             match token {
                 Ok(_tk) => (), // println!("{:#?}", tk),
                 Err(err) => panic!("an error occurred: {:?}", err),
@@ -858,17 +888,4851 @@ mod tests {
         }
 
         let expected_stack = vec![
-            StackEl::new(
-                Token::String,
-                StackElValue::String(String::from("hello world")),
-            ),
-            StackEl::new(Token::Int, StackElValue::Int(69)),
+            StackEl::new(StackElValue::String(String::from("hello world"))),
+            StackEl::new(StackElValue::Int(69)),
         ];
         // println!("stack: {:?}", lex.extras.stack);
         assert_eq!(lex.extras.stack.len(), 2);
         assert_eq!(lex.extras.stack, expected_stack);
     }
 
+    #[test]
+    fn string_escapes() {
+        let code = r#"
+            PUSH "say \"hi\"\n"
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![StackEl::new(StackElValue::String(String::from(
+            "say \"hi\"\n",
+        )))];
+        assert_eq!(lex.extras.stack.len(), 1);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn regex_match() {
+        let code = r#"
+            PUSH "john.doe@example.com"
+            PUSH "^[\\w.]+@\\w+(\\.\\w+)+$"
+            MATCH
+            PUSH "12a4"
+            PUSH "^\\d+$"
+            MATCH
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![
+            StackEl::new(StackElValue::Bool(false)),
+            StackEl::new(StackElValue::Bool(true)),
+        ];
+        assert_eq!(lex.extras.stack.len(), 2);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn invalid_regex_match() {
+        let code = r#"
+            PUSH "hello"
+            PUSH "(hel+o"
+            MATCH
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::MatchError(String::from(
+                            "Invalid pattern: missing closing parenthesis at position 0"
+                        ))
+                    );
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn capture() {
+        let code = r#"
+            PUSH "version 12.4"
+            PUSH "(\\d+)\\.(\\d+)(-beta)?"
+            CAPTURE
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let lifo_vector = LifoVector::from_elements(
+            Type::String,
+            vec![
+                StackElValue::String(String::from("12.4")),
+                StackElValue::String(String::from("12")),
+                StackElValue::String(String::from("4")),
+                StackElValue::String(String::from("")),
+            ],
+        );
+        let expected_stack = vec![StackEl::new(StackElValue::Vector(lifo_vector))];
+        assert_eq!(lex.extras.stack.len(), 1);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn invalid_capture() {
+        let code = r#"
+            PUSH "hello"
+            PUSH "[a-z]{3,1}"
+            CAPTURE
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::CaptureError(String::from(
+                            "Invalid pattern: invalid repetition range at position 5"
+                        ))
+                    );
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn regex_replace() {
+        let code = r#"
+            PUSH "2024-01-31 and 2023-12-01"
+            PUSH "$3/$2/$1"
+            PUSH "(\\d{4})-(\\d\\d)-(\\d\\d)"
+            REGEX_REPLACE
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![StackEl::new(StackElValue::String(String::from(
+            "31/01/2024 and 01/12/2023",
+        )))];
+        assert_eq!(lex.extras.stack.len(), 1);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn invalid_regex_replace() {
+        let code = r#"
+            PUSH "hello"
+            PUSH ""
+            PUSH "l*+"
+            REGEX_REPLACE
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::RegexReplaceError(String::from(
+                            "Invalid pattern: nothing to repeat at position 2"
+                        ))
+                    );
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn regex_long_input() {
+        let code = format!(
+            r#"
+            PUSH "{}"
+            PUSH "a*"
+            MATCH
+            PUSH "{}"
+            PUSH "(a*)*b"
+            MATCH
+        "#,
+            "a".repeat(10_000),
+            "a".repeat(30)
+        );
+        let mut lex = lexer::Token::lexer(&code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![
+            StackEl::new(StackElValue::Bool(false)),
+            StackEl::new(StackElValue::Bool(true)),
+        ];
+        assert_eq!(lex.extras.stack.len(), 2);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn regex_step_limit() {
+        let code = format!(
+            r#"
+            PUSH "{}"
+            PUSH "a{{0,3000}}b"
+            MATCH
+        "#,
+            "a".repeat(5_000)
+        );
+        let mut lex = lexer::Token::lexer(&code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::MatchError(String::from(
+                            "Matching stopped after 10000000 steps, the input is too long for this pattern"
+                        ))
+                    );
+                    return;
+                }
+            }
+        }
+        panic!("the match should have been stopped");
+    }
+
+    #[test]
+    fn to_str() {
+        let code = r#"
+            PUSH " items"
+            PUSH "hello world"
+            SIZE
+            TO_STR
+            CONCAT
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![StackEl::new(StackElValue::String(String::from("11 items")))];
+        assert_eq!(lex.extras.stack.len(), 1);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn vector_to_str() {
+        let code = r#"
+            EMPTY_VECTOR
+            PUSH "a, b"
+            INSERT
+            PUSH "c"
+            INSERT
+            TO_STR
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![StackEl::new(StackElValue::String(String::from(
+            "[\"a, b\", \"c\"]",
+        )))];
+        assert_eq!(lex.extras.stack.len(), 1);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn parse_int() {
+        let code = r#"
+            PUSH "42"
+            PARSE_INT
+            PUSH 8
+            ADD
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![StackEl::new(StackElValue::Int(50))];
+        assert_eq!(lex.extras.stack.len(), 1);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn invalid_parse_int() {
+        let code = r#"
+            PUSH "4x2"
+            PARSE_INT
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::ParseIntError(String::from(
+                            "Cannot parse \"4x2\" as int"
+                        ))
+                    );
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn parse_bool() {
+        let code = r#"
+            PUSH "true"
+            PARSE_BOOL
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![StackEl::new(StackElValue::Bool(true))];
+        assert_eq!(lex.extras.stack.len(), 1);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn invalid_parse_bool() {
+        let code = r#"
+            PUSH 1
+            PARSE_BOOL
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::ParseBoolError(String::from(
+                            "Cannot parse element of type int"
+                        ))
+                    );
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn raw_string() {
+        let code = r##"
+            PUSH r#"{"name": "lifo", "path": "C:\\lifo"}"#
+        "##;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![StackEl::new(StackElValue::String(String::from(
+            r#"{"name": "lifo", "path": "C:\\lifo"}"#,
+        )))];
+        assert_eq!(lex.extras.stack.len(), 1);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn multiline_string() {
+        let code = r#"
+            PUSH """
+                SELECT *
+                  FROM users
+
+                WHERE id = 1
+            """
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![StackEl::new(StackElValue::String(String::from(
+                "SELECT *\n  FROM users\n\nWHERE id = 1"
+            )),
+        )];
+        assert_eq!(lex.extras.stack.len(), 1);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn multiline_string_unicode_indentation() {
+        // the first line is indented with an ideographic space, which takes 3 bytes
+        let code = "PUSH \"\"\"\n\u{3000}first\n  second\n\"\"\"";
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![StackEl::new(StackElValue::String(String::from(
+            "first\n second",
+        )))];
+        assert_eq!(lex.extras.stack.len(), 1);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn interpolated_string() {
+        let code = r#"
+            PUSH 42
+            PUSH "answer"
+            PUSH $"the {0} is {1} {{not {0}}}"
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![
+            StackEl::new(StackElValue::String(String::from(
+                "the answer is 42 {not answer}",
+            ))),
+            StackEl::new(StackElValue::String(String::from("answer"))),
+            StackEl::new(StackElValue::Int(42)),
+        ];
+        assert_eq!(lex.extras.stack.len(), 3);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn invalid_interpolated_string() {
+        let code = r#"
+            PUSH 42
+            PUSH $"the answer is {1}"
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::InvalidInterpolation(String::from(
+                            "Placeholder {1} is out of bound for stack of length 1"
+                        ))
+                    );
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn invalid_raw_string() {
+        let code = r#"
+            PUSH r#"unterminated"
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::InvalidPush(String::from(
+                            "r#\""
+                        ))
+                    );
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn push_char() {
+        let code = r#"
+            PUSH 'a'
+            PUSH '\''
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![
+            StackEl::new(StackElValue::Char('\'')),
+            StackEl::new(StackElValue::Char('a')),
+        ];
+        assert_eq!(lex.extras.stack.len(), 2);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn concat_char() {
+        let code = r#"
+            PUSH "ello"
+            PUSH 'h'
+            CONCAT
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![StackEl::new(StackElValue::String(String::from("hello")))];
+        assert_eq!(lex.extras.stack.len(), 1);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn explode() {
+        let code = r#"
+            PUSH "héllo"
+            EXPLODE
+            INDEX 1
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![StackEl::new(StackElValue::Char('é'))];
+        assert_eq!(lex.extras.stack.len(), 1);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn implode() {
+        let code = r#"
+            PUSH "hello"
+            EXPLODE
+            PUSH '!'
+            INSERT
+            IMPLODE
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![StackEl::new(StackElValue::String(String::from("hello!")))];
+        assert_eq!(lex.extras.stack.len(), 1);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn invalid_implode() {
+        let code = r#"
+            EMPTY_VECTOR
+            PUSH 69
+            INSERT
+            IMPLODE
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::ImplodeError(String::from(
+                            "Cannot implode vector of type vector<int>"
+                        ))
+                    );
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn is_digit() {
+        let code = r#"
+            PUSH 'a'
+            IS_DIGIT
+            PUSH '7'
+            IS_DIGIT
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![
+            StackEl::new(StackElValue::Bool(true)),
+            StackEl::new(StackElValue::Bool(false)),
+        ];
+        assert_eq!(lex.extras.stack.len(), 2);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn is_alpha() {
+        let code = r#"
+            PUSH "a1"
+            INDEX 0
+            IS_ALPHA
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![StackEl::new(StackElValue::Bool(true))];
+        assert_eq!(lex.extras.stack.len(), 1);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn invalid_is_alpha() {
+        let code = r#"
+            PUSH "a"
+            IS_ALPHA
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::IsAlphaError(String::from(
+                            "Only characters can be classified"
+                        ))
+                    );
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn to_upper() {
+        let code = r#"
+            PUSH 'q'
+            TO_UPPER
+            PUSH "abc"
+            TO_UPPER
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![
+            StackEl::new(StackElValue::String(String::from("ABC"))),
+            StackEl::new(StackElValue::Char('Q')),
+        ];
+        assert_eq!(lex.extras.stack.len(), 2);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn push_bytes() {
+        let code = r#"
+            PUSH 0xCAfe00
+            DUP
+            SIZE
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![
+            StackEl::new(StackElValue::Int(3)),
+            StackEl::new(StackElValue::Bytes(vec![0xca, 0xfe, 0x00])),
+        ];
+        assert_eq!(lex.extras.stack.len(), 2);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn invalid_bytes() {
+        let code = r#"
+            PUSH 0xabc
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::InvalidPush(String::from(
+                            "0xabc"
+                        ))
+                    );
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn bytes_index() {
+        let code = r#"
+            PUSH 0x0aff
+            INDEX 1
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![StackEl::new(StackElValue::Int(255))];
+        assert_eq!(lex.extras.stack.len(), 1);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn bytes_slice_concat() {
+        let code = r#"
+            PUSH 0xdead
+            PUSH 0x00beef00
+            SLICE 1 3
+            CONCAT
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![StackEl::new(StackElValue::Bytes(vec![
+            0xbe, 0xef, 0xde, 0xad,
+        ]))];
+        assert_eq!(lex.extras.stack.len(), 1);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn string_slice() {
+        let code = r#"
+            PUSH "héllo world"
+            SLICE 1 5
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![StackEl::new(StackElValue::String(String::from("éllo")))];
+        assert_eq!(lex.extras.stack.len(), 1);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn invalid_slice() {
+        let code = r#"
+            PUSH 0xdead
+            SLICE 1 3
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::SliceError(String::from(
+                            "Out of bound slice 1..3 for bytes of length 2"
+                        ))
+                    );
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn bytes_to_int() {
+        let code = r#"
+            PUSH 0x0102
+            DUP
+            BYTES_TO_INT_LE
+            SWAP
+            BYTES_TO_INT_BE
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![
+            StackEl::new(StackElValue::Int(258)),
+            StackEl::new(StackElValue::Int(513)),
+        ];
+        assert_eq!(lex.extras.stack.len(), 2);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn bytes_to_int_width() {
+        let code = r#"
+            PUSH 0x0102030405060708090a0b0c0d0e0f1011
+            BYTES_TO_INT_BE
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::BytesToIntError(String::from(
+                            "Cannot convert 17 bytes into an int, the maximum is 16"
+                        ))
+                    );
+                    return;
+                }
+            }
+        }
+        panic!("the error should have been reported");
+    }
+
+    #[test]
+    fn int_to_bytes() {
+        let code = r#"
+            PUSH 258
+            INT_TO_BYTES_BE
+            DUP
+            BYTES_TO_INT_BE
+            SWAP
+            PUSH 258
+            INT_TO_BYTES_LE
+            INDEX 0
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![
+            StackEl::new(StackElValue::Int(2)),
+            StackEl::new(StackElValue::Bytes(258_i128.to_be_bytes().to_vec())),
+            StackEl::new(StackElValue::Int(258)),
+        ];
+        assert_eq!(lex.extras.stack.len(), 3);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn bytes_to_str() {
+        let code = r#"
+            PUSH "héllo"
+            STR_TO_BYTES
+            BYTES_TO_STR
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![StackEl::new(StackElValue::String(String::from("héllo")))];
+        assert_eq!(lex.extras.stack.len(), 1);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn invalid_bytes_to_str() {
+        let code = r#"
+            PUSH 0x68c3
+            BYTES_TO_STR
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::BytesToStrError(String::from(
+                            "Invalid UTF-8 sequence at byte 1"
+                        ))
+                    );
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn nested_vector() {
+        let code = r#"
+            EMPTY_VECTOR
+            EMPTY_VECTOR
+            INSERT
+            EMPTY_VECTOR
+            PUSH 1
+            INSERT
+            PUSH 2
+            INSERT
+            INSERT
+            INDEX 1
+            INDEX 0
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![StackEl::new(StackElValue::Int(1))];
+        assert_eq!(lex.extras.stack.len(), 1);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn nested_vector_to_str() {
+        let code = r#"
+            EMPTY_VECTOR
+            EMPTY_VECTOR
+            PUSH "a"
+            INSERT
+            INSERT
+            EMPTY_VECTOR
+            INSERT
+            TO_STR
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![StackEl::new(StackElValue::String(String::from(
+            "[[\"a\"], []]",
+        )))];
+        assert_eq!(lex.extras.stack.len(), 1);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn invalid_nested_insert() {
+        let code = r#"
+            EMPTY_VECTOR
+            EMPTY_VECTOR
+            PUSH 1
+            INSERT
+            INSERT
+            EMPTY_VECTOR
+            PUSH "a"
+            INSERT
+            INSERT
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::InsertError(String::from(
+                            "Cannot insert value of type vector<string> into vector of type vector<vector<int>>"
+                        ))
+                    );
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn any_vector() {
+        let code = r#"
+            EMPTY_VECTOR any
+            PUSH 1
+            INSERT
+            PUSH "two"
+            INSERT
+            EMPTY_VECTOR
+            INSERT
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![StackEl::new(StackElValue::Vector(
+            LifoVector::from_elements(
+                Type::Any,
+                vec![
+                    StackElValue::Int(1),
+                    StackElValue::String(String::from("two")),
+                    StackElValue::Vector(LifoVector::new()),
+                ],
+            ),
+        ))];
+        assert_eq!(lex.extras.stack.len(), 1);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn vector_set() {
+        let code = r#"
+            EMPTY_VECTOR
+            PUSH 1
+            INSERT
+            PUSH 2
+            INSERT
+            PUSH 3
+            INSERT
+            PUSH 20
+            SET 1
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![StackEl::new(StackElValue::Vector(
+            LifoVector::from_elements(
+                Type::Int,
+                vec![
+                    StackElValue::Int(1),
+                    StackElValue::Int(20),
+                    StackElValue::Int(3),
+                ],
+            ),
+        ))];
+        assert_eq!(lex.extras.stack.len(), 1);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn invalid_set_1() {
+        let code = r#"
+            EMPTY_VECTOR
+            PUSH 1
+            INSERT
+            PUSH 2
+            INSERT
+            PUSH 3
+            INSERT
+            PUSH "two"
+            SET 1
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::SetError(String::from(
+                            "Cannot insert value of type string into vector of type vector<int>"
+                        ))
+                    );
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn invalid_set_2() {
+        let code = r#"
+            EMPTY_VECTOR
+            PUSH 1
+            INSERT
+            PUSH 2
+            INSERT
+            PUSH 3
+            INSERT
+            PUSH 4
+            SET 3
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::SetError(String::from(
+                            "Out of bound index 3 for vector of length 3"
+                        ))
+                    );
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn vector_remove() {
+        let code = r#"
+            EMPTY_VECTOR
+            PUSH 1
+            INSERT
+            PUSH 2
+            INSERT
+            PUSH 3
+            INSERT
+            REMOVE 0
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![
+            StackEl::new(StackElValue::Int(1)),
+            StackEl::new(StackElValue::Vector(LifoVector::from_elements(
+                Type::Int,
+                vec![StackElValue::Int(2), StackElValue::Int(3)],
+            )),
+            ),
+        ];
+        assert_eq!(lex.extras.stack.len(), 2);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn invalid_remove() {
+        let code = r#"
+            EMPTY_VECTOR
+            REMOVE 0
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::RemoveError(String::from(
+                            "Out of bound index 0 for vector of length 0"
+                        ))
+                    );
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn vector_insert_at() {
+        let code = r#"
+            EMPTY_VECTOR
+            PUSH 1
+            INSERT
+            PUSH 2
+            INSERT
+            PUSH 3
+            INSERT
+            PUSH 0
+            INSERT_AT 0
+            PUSH 4
+            INSERT_AT 4
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![StackEl::new(StackElValue::Vector(
+            LifoVector::from_elements(
+                Type::Int,
+                vec![
+                    StackElValue::Int(0),
+                    StackElValue::Int(1),
+                    StackElValue::Int(2),
+                    StackElValue::Int(3),
+                    StackElValue::Int(4),
+                ],
+            ),
+        ))];
+        assert_eq!(lex.extras.stack.len(), 1);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn invalid_insert_at() {
+        let code = r#"
+            EMPTY_VECTOR
+            PUSH 1
+            INSERT
+            PUSH 2
+            INSERT
+            PUSH 3
+            INSERT
+            PUSH 5
+            INSERT_AT 5
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::InsertAtError(String::from(
+                            "Out of bound index 5 for vector of length 3"
+                        ))
+                    );
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn vector_pop_back() {
+        let code = r#"
+            EMPTY_VECTOR
+            PUSH 1
+            INSERT
+            PUSH 2
+            INSERT
+            PUSH 3
+            INSERT
+            POP_BACK
+            PUSH 10
+            ADD
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![
+            StackEl::new(StackElValue::Int(13)),
+            StackEl::new(StackElValue::Vector(LifoVector::from_elements(
+                Type::Int,
+                vec![StackElValue::Int(1), StackElValue::Int(2)],
+            )),
+            ),
+        ];
+        assert_eq!(lex.extras.stack.len(), 2);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn invalid_pop_back() {
+        let code = r#"
+            EMPTY_VECTOR
+            POP_BACK
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::PopBackError(String::from(
+                            "Cannot pop an element from an empty vector"
+                        ))
+                    );
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn negative_index() {
+        let code = r#"
+            EMPTY_VECTOR
+            PUSH 69
+            INSERT
+            PUSH 420
+            INSERT
+            DUP
+            INDEX -1
+            SWAP
+            INDEX -2
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![
+            StackEl::new(StackElValue::Int(69)),
+            StackEl::new(StackElValue::Int(420)),
+        ];
+        assert_eq!(lex.extras.stack.len(), 2);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn dynamic_index() {
+        let code = r#"
+            EMPTY_VECTOR
+            PUSH 69
+            INSERT
+            PUSH 420
+            INSERT
+            PUSH 1
+            PUSH 0
+            ADD
+            INDEX
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![StackEl::new(StackElValue::Int(420))];
+        assert_eq!(lex.extras.stack.len(), 1);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn invalid_index_1() {
+        let code = r#"
+            EMPTY_VECTOR
+            PUSH 69
+            INSERT
+            PUSH 420
+            INSERT
+            INDEX 2
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::IndexError(String::from(
+                            "Out of bound index 2 for vector of length 2"
+                        ))
+                    );
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn invalid_index_2() {
+        let code = r#"
+            PUSH "héllo"
+            INDEX -6
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::IndexError(String::from(
+                            "Out of bound index -6 for string of length 5"
+                        ))
+                    );
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn invalid_index_3() {
+        let code = r#"
+            EMPTY_VECTOR
+            PUSH 69
+            INSERT
+            PUSH 420
+            INSERT
+            PUSH "1"
+            INDEX
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::IndexError(String::from(
+                            "Index must be of type int, found string"
+                        ))
+                    );
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn vector_literal() {
+        let code = r#"
+            PUSH [1, 2, 3]
+            DUP
+            INDEX -1
+            SWAP
+            SIZE
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![
+            StackEl::new(StackElValue::Int(3)),
+            StackEl::new(StackElValue::Int(3)),
+        ];
+        assert_eq!(lex.extras.stack.len(), 2);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn nested_vector_literal() {
+        let code = r#"
+            PUSH [[1, 2], [], [3]]
+            TO_STR
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![StackEl::new(StackElValue::String(String::from(
+            "[[1, 2], [], [3]]",
+        )))];
+        assert_eq!(lex.extras.stack.len(), 1);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn empty_vector_literal() {
+        let code = r#"
+            PUSH [] string
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![StackEl::new(StackElValue::Vector(LifoVector::of(
+            Type::String,
+        )))];
+        assert_eq!(lex.extras.stack.len(), 1);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn any_vector_literal() {
+        let code = r#"
+            PUSH [1, 'a', [true]] any
+            INDEX 2
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![StackEl::new(StackElValue::Vector(
+            LifoVector::from_elements(Type::Bool, vec![StackElValue::Bool(true)]),
+        ))];
+        assert_eq!(lex.extras.stack.len(), 1);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn invalid_vector_literal_1() {
+        let code = r#"
+            PUSH [1, "two"]
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::InvalidVector(String::from(
+                            "Cannot insert value of type string into vector of type vector<int>"
+                        ))
+                    );
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn invalid_vector_literal_2() {
+        let code = r#"
+            PUSH [] int
+            POP
+            PUSH [1 2]
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::InvalidVector(String::from(
+                            "Unexpected `2]` in vector literal"
+                        ))
+                    );
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn invalid_vector_literal_3() {
+        let code = r#"
+            PUSH [1] string
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::InvalidVector(String::from(
+                            "Cannot insert value of type int into vector of type vector<string>"
+                        ))
+                    );
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn vector_sort() {
+        let code = r#"
+            PUSH ["pear", "apple", "fig"]
+            SORT
+            TO_STR
+            PUSH [true, false, true]
+            SORT
+            TO_STR
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![
+            StackEl::new(StackElValue::String(String::from("[false, true, true]"))),
+            StackEl::new(StackElValue::String(String::from(
+                "[\"apple\", \"fig\", \"pear\"]",
+            ))),
+        ];
+        assert_eq!(lex.extras.stack.len(), 2);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn invalid_sort() {
+        let code = r#"
+            PUSH [1, 'a'] any
+            SORT
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::SortError(String::from(
+                            "Cannot sort vector of type vector<any>"
+                        ))
+                    );
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn vector_reverse() {
+        let code = r#"
+            PUSH [1, 2, 3]
+            REVERSE
+            INDEX 0
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![StackEl::new(StackElValue::Int(3))];
+        assert_eq!(lex.extras.stack.len(), 1);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn vector_slice() {
+        let code = r#"
+            PUSH [1, 2, 3, 4]
+            SLICE 1 3
+            TO_STR
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![StackEl::new(StackElValue::String(String::from("[2, 3]")))];
+        assert_eq!(lex.extras.stack.len(), 1);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn vector_contains() {
+        let code = r#"
+            PUSH [1, 2, 3]
+            DUP
+            PUSH 2
+            CONTAINS
+            SWAP
+            PUSH 5
+            CONTAINS
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![
+            StackEl::new(StackElValue::Bool(false)),
+            StackEl::new(StackElValue::Bool(true)),
+        ];
+        assert_eq!(lex.extras.stack.len(), 2);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn invalid_contains() {
+        let code = r#"
+            PUSH [1, 2, 3]
+            PUSH "2"
+            CONTAINS
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::ContainsError(String::from(
+                            "Cannot search value of type string in vector of type vector<int>"
+                        ))
+                    );
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn vector_index_of() {
+        let code = r#"
+            PUSH ["a", "b", "b"]
+            DUP
+            PUSH "b"
+            INDEX_OF
+            SWAP
+            PUSH "c"
+            INDEX_OF
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![
+            StackEl::new(StackElValue::Int(3)),
+            StackEl::new(StackElValue::Int(1)),
+        ];
+        assert_eq!(lex.extras.stack.len(), 2);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn map_put() {
+        let code = r#"
+            EMPTY_MAP
+            PUSH "bob"
+            PUSH 42
+            PUT
+            PUSH "alice"
+            PUSH 37
+            PUT
+            PUSH "bob"
+            PUSH 43
+            PUT
+            DUP
+            SIZE
+            SWAP
+            TO_STR
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![
+            StackEl::new(StackElValue::String(String::from(
+                "{\"alice\": 37, \"bob\": 43}",
+            ))),
+            StackEl::new(StackElValue::Int(2)),
+        ];
+        assert_eq!(lex.extras.stack.len(), 2);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn invalid_map_put() {
+        let code = r#"
+            EMPTY_MAP
+            PUSH "bob"
+            PUSH 42
+            PUT
+            PUSH "alice"
+            PUSH 37
+            PUT
+            PUSH 7
+            PUSH 1
+            PUT
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::PutError(String::from(
+                            "Cannot use key of type int in map of type map<string, int>"
+                        ))
+                    );
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn map_get() {
+        let code = r#"
+            EMPTY_MAP
+            PUSH "bob"
+            PUSH 42
+            PUT
+            PUSH "alice"
+            PUSH 37
+            PUT
+            PUSH "alice"
+            GET
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![StackEl::new(StackElValue::Int(37))];
+        assert_eq!(lex.extras.stack.len(), 1);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn invalid_map_get() {
+        let code = r#"
+            EMPTY_MAP
+            PUSH "bob"
+            PUSH 42
+            PUT
+            PUSH "alice"
+            PUSH 37
+            PUT
+            PUSH "carol"
+            GET
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::GetError(String::from(
+                            "Key \"carol\" not found in map"
+                        ))
+                    );
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn map_del_has() {
+        let code = r#"
+            EMPTY_MAP
+            PUSH "bob"
+            PUSH 42
+            PUT
+            PUSH "alice"
+            PUSH 37
+            PUT
+            PUSH "bob"
+            DEL
+            DUP
+            PUSH "bob"
+            HAS
+            SWAP
+            PUSH "alice"
+            HAS
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![
+            StackEl::new(StackElValue::Bool(true)),
+            StackEl::new(StackElValue::Bool(false)),
+        ];
+        assert_eq!(lex.extras.stack.len(), 2);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn map_keys_values() {
+        let code = r#"
+            EMPTY_MAP int bool
+            PUSH 3
+            PUSH true
+            PUT
+            PUSH 1
+            PUSH false
+            PUT
+            DUP
+            KEYS
+            TO_STR
+            SWAP
+            VALUES
+            TO_STR
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![
+            StackEl::new(StackElValue::String(String::from("[false, true]"))),
+            StackEl::new(StackElValue::String(String::from("[1, 3]"))),
+        ];
+        assert_eq!(lex.extras.stack.len(), 2);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn invalid_map_key() {
+        let code = r#"
+            EMPTY_MAP
+            PUSH { DUP }
+            PUSH 1
+            PUT
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::PutError(String::from(
+                            "Cannot use value of type quotation as a map key"
+                        ))
+                    );
+                    return;
+                }
+            }
+        }
+        panic!("the error should have been reported");
+    }
+
+    #[test]
+    fn vector_map_key() {
+        let code = r#"
+            EMPTY_MAP
+            PUSH [1]
+            PUSH 1
+            PUT
+            PUSH [1]
+            GET
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![StackEl::new(StackElValue::Int(1))];
+        assert_eq!(lex.extras.stack.len(), 1);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn set_insert() {
+        let code = r#"
+            EMPTY_SET
+            PUSH 3
+            INSERT
+            PUSH 1
+            INSERT
+            PUSH 3
+            INSERT
+            PUSH 2
+            INSERT
+            DUP
+            SIZE
+            SWAP
+            TO_STR
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![
+            StackEl::new(StackElValue::String(String::from("{1, 2, 3}"))),
+            StackEl::new(StackElValue::Int(3)),
+        ];
+        assert_eq!(lex.extras.stack.len(), 2);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn set_contains_del() {
+        let code = r#"
+            EMPTY_SET string
+            PUSH "a"
+            INSERT
+            PUSH "b"
+            INSERT
+            PUSH "a"
+            DEL
+            DUP
+            PUSH "a"
+            CONTAINS
+            SWAP
+            PUSH "b"
+            CONTAINS
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![
+            StackEl::new(StackElValue::Bool(true)),
+            StackEl::new(StackElValue::Bool(false)),
+        ];
+        assert_eq!(lex.extras.stack.len(), 2);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn invalid_set_insert() {
+        let code = r#"
+            EMPTY_SET int
+            PUSH "a"
+            INSERT
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::InsertError(String::from(
+                            "Cannot insert value of type string into set of type set<int>"
+                        ))
+                    );
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn invalid_union() {
+        let code = r#"
+            EMPTY_SET int
+            EMPTY_SET string
+            UNION
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::UnionError(String::from(
+                            "Cannot combine set of type set<string> with set of type set<int>"
+                        ))
+                    );
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn set_union_intersect_diff() {
+        let code = r#"
+            PUSH [2, 3, 4]
+            TO_SET
+            PUSH [3, 2, 1, 1]
+            TO_SET
+            DIFF
+            TO_VECTOR
+            PUSH [1, 2]
+            TO_SET
+            PUSH [2, 5]
+            TO_SET
+            UNION
+            TO_STR
+            PUSH [1, 2]
+            TO_SET
+            PUSH [2, 5]
+            TO_SET
+            INTERSECT
+            TO_STR
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![
+            StackEl::new(StackElValue::String(String::from("{2}"))),
+            StackEl::new(StackElValue::String(String::from("{1, 2, 5}"))),
+            StackEl::new(StackElValue::Vector(LifoVector::from_elements(
+                Type::Int,
+                vec![StackElValue::Int(1)],
+            ))),
+        ];
+        assert_eq!(lex.extras.stack.len(), 3);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn pair() {
+        let code = r#"
+            PUSH "a"
+            PUSH 1
+            PAIR
+            DUP
+            CAR
+            SWAP
+            CDR
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![
+            StackEl::new(StackElValue::String(String::from("a"))),
+            StackEl::new(StackElValue::Int(1)),
+        ];
+        assert_eq!(lex.extras.stack.len(), 2);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn unpair() {
+        let code = r#"
+            PUSH "a"
+            PUSH 1
+            PAIR
+            UNPAIR
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![
+            StackEl::new(StackElValue::Int(1)),
+            StackEl::new(StackElValue::String(String::from("a"))),
+        ];
+        assert_eq!(lex.extras.stack.len(), 2);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn pair_in_vector() {
+        let code = r#"
+            EMPTY_VECTOR
+            PUSH "b"
+            PUSH 2
+            PAIR
+            INSERT
+            PUSH "a"
+            PUSH 1
+            PAIR
+            INSERT
+            SORT
+            DUP
+            INDEX 0
+            PUSH "a"
+            PUSH 1
+            PAIR
+            EQ
+            SWAP
+            INDEX 1
+            PUSH "a"
+            PUSH 1
+            PAIR
+            NEQ
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![
+            StackEl::new(StackElValue::Bool(true)),
+            StackEl::new(StackElValue::Bool(true)),
+        ];
+        assert_eq!(lex.extras.stack.len(), 2);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn pair_to_str() {
+        let code = r#"
+            PUSH "a"
+            PUSH 1
+            PAIR
+            TO_STR
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![StackEl::new(StackElValue::String(String::from(
+            "(1, \"a\")",
+        )))];
+        assert_eq!(lex.extras.stack.len(), 1);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn invalid_car() {
+        let code = r#"
+            PUSH 1
+            CAR
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::CarError(String::from(
+                            "Cannot get the first element of element of type int"
+                        ))
+                    );
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn record() {
+        let code = r#"
+            PUSH #{name: "bob", age: 42, active: true}
+            DUP
+            GET_FIELD age
+            SWAP
+            TO_STR
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![
+            StackEl::new(StackElValue::String(String::from(
+                r#"#{active: true, age: 42, name: "bob"}"#,
+            ))),
+            StackEl::new(StackElValue::Int(42)),
+        ];
+        assert_eq!(lex.extras.stack.len(), 2);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn record_set_field() {
+        let code = r#"
+            PUSH #{name: "bob", age: 42}
+            PUSH 43
+            SET_FIELD age
+            DUP
+            PUSH #{age: 43, name: "bob"}
+            EQ
+            SWAP
+            GET_FIELD age
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![
+            StackEl::new(StackElValue::Int(43)),
+            StackEl::new(StackElValue::Bool(true)),
+        ];
+        assert_eq!(lex.extras.stack.len(), 2);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn record_in_vector() {
+        let code = r#"
+            PUSH [#{name: "bob", age: 42}, #{name: "alice", age: 37}]
+            INDEX 1
+            GET_FIELD name
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![StackEl::new(StackElValue::String(String::from("alice")))];
+        assert_eq!(lex.extras.stack.len(), 1);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn invalid_get_field() {
+        let code = r#"
+            PUSH #{name: "bob"}
+            GET_FIELD age
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::GetFieldError(String::from(
+                            "Record of type record<name: string> has no field `age`"
+                        ))
+                    );
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn invalid_set_field() {
+        let code = r#"
+            PUSH #{name: "bob"}
+            PUSH 3
+            SET_FIELD name
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::SetFieldError(String::from(
+                            "Cannot set field `name` of type string to value of type int"
+                        ))
+                    );
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn invalid_record_literal() {
+        let code = r#"
+            PUSH #{name: "bob", name: "alice"}
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::InvalidRecord(String::from(
+                            "Duplicate field `name` in record"
+                        ))
+                    );
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn option() {
+        let code = r#"
+            PUSH 1
+            SOME
+            NONE int
+            PUSH [3, 4]
+            INDEX_OPT 1
+            UNWRAP
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![
+            StackEl::new(StackElValue::Int(4)),
+            StackEl::new(StackElValue::Option(Type::Int, None)),
+            StackEl::new(StackElValue::Option(
+                Type::Int,
+                Some(Box::new(StackElValue::Int(1))),
+            )),
+        ];
+        assert_eq!(lex.extras.stack.len(), 3);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn index_opt_out_of_bounds() {
+        let code = r#"
+            PUSH [3, 4]
+            PUSH 2
+            INDEX_OPT
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![StackEl::new(StackElValue::Option(Type::Int, None))];
+        assert_eq!(lex.extras.stack.len(), 1);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn index_opt_huge_index() {
+        let code = r#"
+            PUSH "abc"
+            PUSH 18446744073709551615
+            INDEX_OPT
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![StackEl::new(StackElValue::Option(Type::Char, None))];
+        assert_eq!(lex.extras.stack.len(), 1);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn index_huge_index() {
+        let code = r#"
+            PUSH "abc"
+            PUSH 18446744073709551615
+            INDEX
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::IndexError(String::from(
+                            "Out of bound index 18446744073709551615 for string of length 3"
+                        ))
+                    );
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn if_none() {
+        let code = r#"
+            PUSH [3, 4]
+            DUP
+            INDEX_OPT 0
+            IF_NONE missing
+            SWAP
+            INDEX_OPT 5
+            IF_NONE missing
+            PUSH 1
+            missing:
+            PUSH 0
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![
+            StackEl::new(StackElValue::Int(0)),
+            StackEl::new(StackElValue::Int(3)),
+        ];
+        assert_eq!(lex.extras.stack.len(), 2);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn invalid_unwrap() {
+        let code = r#"
+            NONE int
+            UNWRAP
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::UnwrapError(String::from(
+                            "Cannot unwrap none value of type option<int>"
+                        ))
+                    );
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn invalid_if_none() {
+        let code = r#"
+            PUSH 5
+            IF_NONE missing
+            missing:
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::IfNoneError(String::from(
+                            "Top element must be an option, found int"
+                        ))
+                    );
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn or_values() {
+        let code = r#"
+            PUSH 1
+            LEFT string
+            PUSH "error"
+            RIGHT int
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![
+            StackEl::new(StackElValue::Right(
+                Type::Int,
+                Box::new(StackElValue::String(String::from("error"))),
+            )),
+            StackEl::new(StackElValue::Left(
+                Box::new(StackElValue::Int(1)),
+                Type::String,
+            )),
+        ];
+        assert_eq!(lex.extras.stack.len(), 2);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn if_left() {
+        let code = r#"
+            PUSH "error"
+            RIGHT int
+            IF_LEFT success
+            PUSH 1
+            LEFT string
+            IF_LEFT success
+            PUSH 0
+            success:
+            PUSH 2
+            ADD
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![
+            StackEl::new(StackElValue::Int(3)),
+            StackEl::new(StackElValue::String(String::from("error"))),
+        ];
+        assert_eq!(lex.extras.stack.len(), 2);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn invalid_or_vector() {
+        let code = r#"
+            EMPTY_VECTOR
+            PUSH 1
+            LEFT string
+            INSERT
+            PUSH "a"
+            RIGHT bool
+            INSERT
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::InsertError(String::from(
+                            "Cannot insert value of type or<bool, string> into vector of type vector<or<int, string>>"
+                        ))
+                    );
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn invalid_left() {
+        let code = r#"
+            PUSH 1
+            LEFT
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::LeftError(String::from(
+                            "LEFT must be followed by the type of the right value"
+                        ))
+                    );
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn invalid_if_left() {
+        let code = r#"
+            PUSH 1
+            IF_LEFT success
+            success:
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::IfLeftError(String::from(
+                            "Cannot unwrap value of type int, only or values can be unwrapped"
+                        ))
+                    );
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn push_family_order() {
+        let code = r#"
+            PUSH_INT 1
+            PUSH_BOOL true
+            PUSH_STR "a"
+            PUSH 2
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![
+            StackEl::new(StackElValue::Int(2)),
+            StackEl::new(StackElValue::String(String::from("a"))),
+            StackEl::new(StackElValue::Bool(true)),
+            StackEl::new(StackElValue::Int(1)),
+        ];
+        assert_eq!(lex.extras.stack.len(), 4);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn push_ascription() {
+        let code = r#"
+            PUSH int 5
+            PUSH vector<string> []
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![
+            StackEl::new(StackElValue::Vector(LifoVector::of(Type::String))),
+            StackEl::new(StackElValue::Int(5)),
+        ];
+        assert_eq!(lex.extras.stack.len(), 2);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn push_any_ascription() {
+        let code = r#"
+            PUSH any 5
+            PUSH any "a"
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![
+            StackEl::new(StackElValue::String(String::from("a"))),
+            StackEl::new(StackElValue::Int(5)),
+        ];
+        assert_eq!(lex.extras.stack.len(), 2);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn typed_empty_vector() {
+        let code = r#"
+            EMPTY_VECTOR map<string, int>
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![StackEl::new(StackElValue::Vector(LifoVector::of(
+            Type::Map(Box::new(Type::String), Box::new(Type::Int)),
+        )))];
+        assert_eq!(lex.extras.stack.len(), 1);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn invalid_typed_empty_vector() {
+        let code = r#"
+            EMPTY_VECTOR int
+            PUSH "a"
+            INSERT
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::InsertError(String::from(
+                            "Cannot insert value of type string into vector of type vector<int>"
+                        ))
+                    );
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn invalid_push_ascription() {
+        let code = r#"
+            PUSH int "a"
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::InvalidPush(String::from(
+                            "Cannot push value of type string as type int"
+                        ))
+                    );
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn size_arithmetic() {
+        let code = r#"
+            PUSH "abc"
+            SIZE
+            PUSH 5
+            SUB
+            PUSH [1, 2]
+            SIZE
+            MUL
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![StackEl::new(StackElValue::Int(4))];
+        assert_eq!(lex.extras.stack.len(), 1);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn type_of() {
+        let code = r#"
+            PUSH [1, 2]
+            TYPEOF
+            PUSH "a"
+            PUSH 1
+            PAIR
+            TYPEOF
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![
+            StackEl::new(StackElValue::String(String::from("pair<int, string>"))),
+            StackEl::new(StackElValue::String(String::from("vector<int>"))),
+        ];
+        assert_eq!(lex.extras.stack.len(), 2);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn invalid_type_of() {
+        let code = r#"
+            TYPEOF
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::TypeOfError(String::from(
+                            "Stack must be at least 1 element deep"
+                        ))
+                    );
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn neq_size() {
+        let code = r#"
+            PUSH 2
+            PUSH "ab"
+            SIZE
+            NEQ
+            PUSH []
+            PUSH [1]
+            POP_BACK
+            POP
+            EQ
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![
+            StackEl::new(StackElValue::Bool(true)),
+            StackEl::new(StackElValue::Bool(false)),
+        ];
+        assert_eq!(lex.extras.stack.len(), 2);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn compare() {
+        let code = r#"
+            PUSH 2
+            PUSH 1
+            COMPARE
+            PUSH [1, 2]
+            PUSH [1, 3]
+            COMPARE
+            PUSH [[1], [2]]
+            PUSH [[1]]
+            COMPARE
+            PUSH #{a: 1, b: "x"}
+            PUSH #{b: "x", a: 1}
+            COMPARE
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![
+            StackEl::new(StackElValue::Int(0)),
+            StackEl::new(StackElValue::Int(-1)),
+            StackEl::new(StackElValue::Int(1)),
+            StackEl::new(StackElValue::Int(-1)),
+        ];
+        assert_eq!(lex.extras.stack.len(), 4);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn invalid_compare() {
+        let code = r#"
+            PUSH 1
+            PUSH "a"
+            COMPARE
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::CompareError(String::from(
+                            "Cannot compare value of type string with value of type int"
+                        ))
+                    );
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn negative_compare_result() {
+        let code = r#"
+            PUSH 2
+            PUSH 1
+            COMPARE
+            INT_TO_BYTES_BE
+            DUP
+            BYTES_TO_INT_BE
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![
+            StackEl::new(StackElValue::Int(-1)),
+            StackEl::new(StackElValue::Bytes(vec![0xff; 16])),
+        ];
+        assert_eq!(lex.extras.stack.len(), 2);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn if_else() {
+        let code = r#"
+            PUSH 1
+            PUSH 2
+            EQ
+            IF
+                PUSH "equal"
+            ELSE
+                PUSH "different"
+            END
+            PUSH true
+            IF
+                PUSH 1
+            END
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![
+            StackEl::new(StackElValue::Int(1)),
+            StackEl::new(
+                StackElValue::String(String::from("different")),
+            ),
+        ];
+        assert_eq!(lex.extras.stack.len(), 2);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn nested_if() {
+        let code = r#"
+            PUSH true
+            IF
+                PUSH false
+                IF
+                    PUSH 1
+                ELSE
+                    PUSH true
+                    IF
+                        PUSH 2
+                    END
+                END
+            ELSE
+                PUSH 3
+            END
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![StackEl::new(StackElValue::Int(2))];
+        assert_eq!(lex.extras.stack.len(), 1);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn if_with_labels() {
+        let code = r#"
+            PUSH true
+            IF
+                JUMP done
+                PUSH 1
+            ELSE
+                PUSH 2
+            END
+            done:
+            PUSH 3
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![StackEl::new(StackElValue::Int(3))];
+        assert_eq!(lex.extras.stack.len(), 1);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn invalid_if() {
+        let code = r#"
+            PUSH true
+            IF
+                PUSH 1
+            ELSE
+                PUSH 2
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::InvalidBlock(String::from(
+                            "IF without matching END at 35..37"
+                        ))
+                    );
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn invalid_end() {
+        let code = r#"
+            PUSH 1
+            END
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::InvalidBlock(String::from(
+                            "END without matching block at 32..35"
+                        ))
+                    );
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn invalid_if_condition() {
+        let code = r#"
+            PUSH 1
+            IF
+            END
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::IfError(String::from(
+                            "Top element must be a boolean value"
+                        ))
+                    );
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn while_loop() {
+        let code = r#"
+            PUSH 0
+            WHILE
+                DUP
+                PUSH 5
+                COMPARE
+                PUSH 1
+                EQ
+            DO
+                PUSH 1
+                ADD
+            END
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![StackEl::new(StackElValue::Int(5))];
+        assert_eq!(lex.extras.stack.len(), 1);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn repeat() {
+        let code = r#"
+            PUSH 1
+            PUSH 3
+            REPEAT
+                PUSH 2
+                REPEAT
+                    PUSH 2
+                    MUL
+                END
+            END
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![StackEl::new(StackElValue::Int(64))];
+        assert_eq!(lex.extras.stack.len(), 1);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn while_with_labels() {
+        let code = r#"
+            PUSH 0
+            WHILE
+                PUSH true
+            DO
+                PUSH 1
+                ADD
+                DUP
+                PUSH 3
+                EQ
+                JUMPI done
+            END
+            done:
+            PUSH 9
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![
+            StackEl::new(StackElValue::Int(9)),
+            StackEl::new(StackElValue::Int(3)),
+        ];
+        assert_eq!(lex.extras.stack.len(), 2);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn invalid_repeat() {
+        let code = r#"
+            PUSH "a"
+            REPEAT
+            END
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::RepeatError(String::from(
+                            "The number of repetitions must be of type int, found string"
+                        ))
+                    );
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn invalid_while() {
+        let code = r#"
+            WHILE
+                PUSH true
+            END
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::InvalidBlock(String::from(
+                            "WHILE without matching DO at 13..18"
+                        ))
+                    );
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn infinite_loop() {
+        let code = r#"
+            WHILE
+                PUSH true
+            DO
+            END
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        lex.extras.limits.max_steps = 10;
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::WhileError(String::from(
+                            "Execution stopped after 10 steps, the program may never end"
+                        ))
+                    );
+                    return;
+                }
+            }
+        }
+        panic!("the loop should have been stopped");
+    }
+
+    #[test]
+    fn nested_loops_limit() {
+        let code = r#"
+            PUSH 4
+            REPEAT
+                PUSH 4
+                REPEAT
+                END
+            END
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        lex.extras.limits.max_steps = 10;
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::RepeatError(String::from(
+                            "Execution stopped after 10 steps, the program may never end"
+                        ))
+                    );
+                    return;
+                }
+            }
+        }
+        panic!("the loops should have been stopped");
+    }
+
+    #[test]
+    fn call_function() {
+        let code = r#"
+            DEF square
+                DUP
+                MUL
+            END
+            PUSH 7
+            CALL square
+            PUSH 3
+            CALL square
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![
+            StackEl::new(StackElValue::Int(9)),
+            StackEl::new(StackElValue::Int(49)),
+        ];
+        assert_eq!(lex.extras.stack.len(), 2);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn recursive_function() {
+        let code = r#"
+            DEF factorial
+                DUP
+                PUSH 1
+                EQ
+                IF
+                    RET
+                END
+                DUP
+                PUSH 1
+                SWAP
+                SUB
+                CALL factorial
+                MUL
+            END
+            PUSH 10
+            CALL factorial
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![StackEl::new(StackElValue::Int(3628800))];
+        assert_eq!(lex.extras.stack.len(), 1);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn early_return() {
+        let code = r#"
+            DEF count
+                PUSH 0
+                WHILE
+                    PUSH true
+                DO
+                    PUSH 1
+                    ADD
+                    DUP
+                    PUSH 5
+                    EQ
+                    IF
+                        RET
+                    END
+                END
+                PUSH "unreachable"
+            END
+            CALL count
+            PUSH "after"
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![
+            StackEl::new(StackElValue::String(String::from("after"))),
+            StackEl::new(StackElValue::Int(5)),
+        ];
+        assert_eq!(lex.extras.stack.len(), 2);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn unknown_function() {
+        let code = r#"
+            PUSH 1
+            CALL missing
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::CallError(String::from(
+                            "Unknown function `missing`"
+                        ))
+                    );
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn return_outside_function() {
+        let code = r#"
+            PUSH 1
+            RET
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::RetError(String::from(
+                            "RET can only be used in a function or a quotation"
+                        ))
+                    );
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn jump_out_of_function() {
+        let code = r#"
+            DEF escape
+                JUMP outside
+            END
+            CALL escape
+            outside:
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::CallError(String::from(
+                            "Label `outside` not found in function `escape`"
+                        ))
+                    );
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn endless_recursion() {
+        let code = r#"
+            DEF forever
+                CALL forever
+            END
+            CALL forever
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        lex.extras.limits.max_call_depth = 10;
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::CallError(String::from(
+                            "Call depth limit of 10 reached when calling function `forever`, the recursion may never end"
+                        ))
+                    );
+                    return;
+                }
+            }
+        }
+        panic!("the recursion should have been stopped");
+    }
+
+    #[test]
+    fn exec_quotation() {
+        let code = r#"
+            PUSH 7
+            { DUP MUL }
+            EXEC
+            { }
+            EXEC
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![StackEl::new(StackElValue::Int(49))];
+        assert_eq!(lex.extras.stack.len(), 1);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn push_quotation() {
+        let code = r#"
+            { DUP MUL }
+            PUSH quotation { PUSH "}" }
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![
+            StackEl::new(
+                StackElValue::Quotation(String::from("PUSH \"}\"")),
+            ),
+            StackEl::new(
+                StackElValue::Quotation(String::from("DUP MUL")),
+            ),
+        ];
+        assert_eq!(lex.extras.stack.len(), 2);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn quotations_in_vector() {
+        let code = r#"
+            PUSH 3
+            PUSH [{ DUP ADD }, { DUP MUL }]
+            PUSH 1
+            INDEX
+            EXEC
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![StackEl::new(StackElValue::Int(9))];
+        assert_eq!(lex.extras.stack.len(), 1);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn return_from_quotation() {
+        let code = r#"
+            { PUSH 1 RET PUSH 2 }
+            EXEC
+            PUSH 3
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![
+            StackEl::new(StackElValue::Int(3)),
+            StackEl::new(StackElValue::Int(1)),
+        ];
+        assert_eq!(lex.extras.stack.len(), 2);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn exec_non_quotation() {
+        let code = r#"
+            PUSH 1
+            EXEC
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::ExecError(String::from(
+                            "Cannot execute element of type int"
+                        ))
+                    );
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn unterminated_quotation() {
+        let code = r#"
+            PUSH 1
+            { DUP
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::InvalidQuotation(String::from(
+                            "Unterminated quotation literal"
+                        ))
+                    );
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn unexpected_quotation_end() {
+        let code = r#"
+            PUSH 1
+            DUP }
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::InvalidQuotation(String::from(
+                            "Unexpected `}` without matching `{`"
+                        ))
+                    );
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn invalid_block_in_quotation() {
+        let code = r#"
+            { IF DUP }
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::InvalidQuotation(String::from(
+                            "IF without matching END at 0..2 in quotation `{ IF DUP }`"
+                        ))
+                    );
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn map_vector() {
+        let code = r#"
+            PUSH [1, 2, 3]
+            { DUP MUL }
+            MAP
+            PUSH [1, 2]
+            { TO_STR }
+            MAP
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![
+            StackEl::new(StackElValue::Vector(LifoVector::from_elements(
+                Type::String,
+                vec![
+                    StackElValue::String(String::from("1")),
+                    StackElValue::String(String::from("2")),
+                ],
+            ))),
+            StackEl::new(StackElValue::Vector(LifoVector::from_elements(
+                Type::Int,
+                vec![StackElValue::Int(1), StackElValue::Int(4), StackElValue::Int(9)],
+            ))),
+        ];
+        assert_eq!(lex.extras.stack.len(), 2);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn filter_vector() {
+        let code = r#"
+            PUSH [1, 2, 3, 2]
+            { PUSH 2 EQ }
+            FILTER
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![StackEl::new(
+            StackElValue::Vector(LifoVector::from_elements(
+                Type::Int,
+                vec![StackElValue::Int(2), StackElValue::Int(2)],
+            )),
+        )];
+        assert_eq!(lex.extras.stack.len(), 1);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn fold_vector() {
+        let code = r#"
+            PUSH [1, 2, 3]
+            PUSH ""
+            { TO_STR SWAP CONCAT }
+            FOLD
+            PUSH [1, 2, 3]
+            PUSH 0
+            { ADD }
+            FOLD
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![
+            StackEl::new(StackElValue::Int(6)),
+            StackEl::new(StackElValue::String(String::from("123"))),
+        ];
+        assert_eq!(lex.extras.stack.len(), 2);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn each_vector() {
+        let code = r#"
+            PUSH 0
+            PUSH [1, 2, 3]
+            { ADD }
+            EACH
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![StackEl::new(StackElValue::Int(6))];
+        assert_eq!(lex.extras.stack.len(), 1);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn map_mixed_types() {
+        let code = r#"
+            PUSH [1, 2]
+            { PUSH 1 EQ IF PUSH "one" ELSE PUSH 2 END }
+            MAP
+            PUSH [1, "a"] any
+            { }
+            MAP
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![
+            StackEl::new(StackElValue::Vector(LifoVector::from_elements(
+                Type::Any,
+                vec![StackElValue::Int(1), StackElValue::String(String::from("a"))],
+            ))),
+            StackEl::new(StackElValue::Vector(LifoVector::from_elements(
+                Type::Any,
+                vec![StackElValue::String(String::from("one")), StackElValue::Int(2)],
+            ))),
+        ];
+        assert_eq!(lex.extras.stack.len(), 2);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn filter_non_boolean() {
+        let code = r#"
+            PUSH [1, 2]
+            { DUP ADD }
+            FILTER
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::FilterError(String::from(
+                            "The block must leave a boolean value, found int"
+                        ))
+                    );
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn map_extra_values() {
+        let code = r#"
+            PUSH [1, 2]
+            { DUP }
+            MAP
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::MapError(String::from(
+                            "The block must replace its arguments with exactly one value"
+                        ))
+                    );
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn fold_non_vector() {
+        let code = r#"
+            PUSH 1
+            PUSH 0
+            { ADD }
+            FOLD
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::FoldError(String::from(
+                            "Cannot iterate over element of type int"
+                        ))
+                    );
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn jump_dyn() {
+        let code = r#"
+            PUSH "second"
+            JUMP_DYN
+            first:
+                PUSH 1
+            second:
+                PUSH 2
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![StackEl::new(StackElValue::Int(2))];
+        assert_eq!(lex.extras.stack.len(), 1);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn jump_dyn_unknown_label() {
+        let code = r#"
+            PUSH "third"
+            JUMP_DYN
+            first:
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::JumpDynError(String::from(
+                            "Unknown label `third` at 38..46"
+                        ))
+                    );
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn jump_dyn_non_string() {
+        let code = r#"
+            PUSH 1
+            JUMP_DYN
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::JumpDynError(String::from(
+                            "Cannot jump to element of type int"
+                        ))
+                    );
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn switch() {
+        let code = r#"
+            PUSH "sub"
+            SWITCH ["add" => add, "sub" => sub] other
+            add:
+                PUSH 1
+                JUMP done
+            sub:
+                PUSH 2
+                JUMP done
+            other:
+                PUSH 3
+            done:
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![StackEl::new(StackElValue::Int(2))];
+        assert_eq!(lex.extras.stack.len(), 1);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn switch_default() {
+        let code = r#"
+            PUSH 5
+            SWITCH [1 => one, 2 => two] other
+            other:
+                PUSH "large"
+                JUMP done
+            one:
+            two:
+                PUSH "small"
+            done:
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![StackEl::new(StackElValue::String(String::from("large")))];
+        assert_eq!(lex.extras.stack.len(), 1);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn switch_unknown_label() {
+        let code = r#"
+            PUSH 1
+            SWITCH [1 => one] other
+            other:
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::SwitchError(String::from(
+                            "Unknown label `one` at 45..48"
+                        ))
+                    );
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn switch_case_type() {
+        let code = r#"
+            PUSH "one"
+            SWITCH [1 => one] other
+            one:
+            other:
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::SwitchError(String::from(
+                            "Cannot match element of type string against case of type int"
+                        ))
+                    );
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn invalid_switch_table() {
+        let code = r#"
+            PUSH 1
+            SWITCH [1 one] other
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::SwitchError(String::from(
+                            "Unexpected `one]` in switch table"
+                        ))
+                    );
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn skipped_vector_literal() {
+        let code = r#"
+            JUMP skip
+            PUSH [$"{5}"]
+            skip:
+            PUSH 1
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![StackEl::new(StackElValue::Int(1))];
+        assert_eq!(lex.extras.stack.len(), 1);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn empty_map_any_keys() {
+        let code = r#"
+            EMPTY_MAP any int
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::EmptyMapError(String::from(
+                            "Map keys can't contain values of type any, they must be ordered"
+                        ))
+                    );
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn empty_set_any() {
+        let code = r#"
+            EMPTY_SET any
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::EmptySetError(String::from(
+                            "Set elements can't contain values of type any, they must be ordered"
+                        ))
+                    );
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn program_checked_before_running() {
+        let code = r#"PUSH "hi" LOG PUSH true IF PUSH 1 END END"#;
+        let state = lexer::State::checked(code);
+        assert_eq!(
+            state.err(),
+            Some(LexingError::InvalidBlock(String::from(
+                "END without matching block at 38..41"
+            )))
+        );
+
+        let code = r#"PUSH 1 LOG { PUSH true IF }"#;
+        let state = lexer::State::checked(code);
+        assert_eq!(
+            state.err(),
+            Some(LexingError::InvalidQuotation(String::from(
+                "IF without matching END at 10..12 in quotation `{ PUSH true IF }`"
+            )))
+        );
+    }
+
+    #[test]
+    fn loop_in_quotation() {
+        let code = r#"
+            PUSH [1, 2]
+            {
+                PUSH 2
+                REPEAT
+                    PUSH true
+                    IF
+                        DUP
+                        ADD
+                    END
+                END
+            }
+            MAP
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let lifo_vector = LifoVector::from_elements(
+            Type::Int,
+            vec![StackElValue::Int(4), StackElValue::Int(8)],
+        );
+        let expected_stack = vec![StackEl::new(StackElValue::Vector(lifo_vector))];
+        assert_eq!(lex.extras.stack.len(), 1);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn jump_dyn_span_in_loop() {
+        let code = r#"PUSH 1 PUSH 2 PUSH 1 REPEAT PUSH "nope" JUMP_DYN END"#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::JumpDynError(String::from(
+                            "Unknown label `nope` at 40..48"
+                        ))
+                    );
+                    return;
+                }
+            }
+        }
+        panic!("the unknown label should have been reported");
+    }
+
+    #[test]
+    fn jump_dyn_backward() {
+        let code = r#"JUMP start back: PUSH 1 start: PUSH "back" JUMP_DYN PUSH 99"#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::JumpDynError(String::from(
+                            "Unknown label `back` at 43..51"
+                        ))
+                    );
+                    return;
+                }
+            }
+        }
+        panic!("the backward jump should have been reported");
+    }
+
+    #[test]
+    fn vector_literal_opcode_not_run() {
+        let code = r#"
+            PUSH [LOG]
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::InvalidVector(String::from(
+                            "Unexpected `LOG]` in vector literal"
+                        ))
+                    );
+                    return;
+                }
+            }
+        }
+        panic!("the error should have been reported");
+    }
+
+    #[test]
+    fn skipped_vector_literal_loop_not_run() {
+        let code = r#"
+            PUSH 1
+            JUMP x
+            PUSH [WHILE PUSH true DO END]
+            x:
+            PUSH 2
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::InvalidVector(String::from(
+                            "Unexpected `WHILE` in vector literal"
+                        ))
+                    );
+                    return;
+                }
+            }
+        }
+        panic!("the error should have been reported");
+    }
+
+    #[test]
+    fn vector_literal_too_deep() {
+        let code = r#"
+            PUSH [[[[[[[[[[[[[[[[[[[[[[[[[[[[[[[[[[[[[[[[[[[[[[[[[[[[[[[[[[[[[[[[[[[[[[[[[[[[[[[[[[[[[[[[[[[[[[[[[[[[[]]]]]]]]]]]]]]]]]]]]]]]]]]]]]]]]]]]]]]]]]]]]]]]]]]]]]]]]]]]]]]]]]]]]]]]]]]]]]]]]]]]]]]]]]]]]]]]]]]]]]
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::InvalidVector(String::from(
+                            "Literals can't be nested more than 100 levels deep"
+                        ))
+                    );
+                    return;
+                }
+            }
+        }
+        panic!("the error should have been reported");
+    }
+
+    #[test]
+    fn map_key_nested_any() {
+        let code = r#"
+            EMPTY_MAP
+            EMPTY_VECTOR any
+            PUSH 1
+            INSERT
+            PUSH 1
+            PUT
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::PutError(String::from(
+                            "Cannot use value of type vector<any> as a map key"
+                        ))
+                    );
+                    return;
+                }
+            }
+        }
+        panic!("the error should have been reported");
+    }
+
+    #[test]
+    fn empty_map_nested_any_keys() {
+        let code = r#"
+            EMPTY_MAP vector<any> int
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::EmptyMapError(String::from(
+                            "Map keys can't contain values of type any, they must be ordered"
+                        ))
+                    );
+                    return;
+                }
+            }
+        }
+        panic!("the error should have been reported");
+    }
+
+    #[test]
+    fn set_element_nested_any() {
+        let code = r#"
+            EMPTY_SET
+            EMPTY_VECTOR any
+            PUSH 1
+            INSERT
+            INSERT
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::InsertError(String::from(
+                            "Cannot use value of type vector<any> as a set element"
+                        ))
+                    );
+                    return;
+                }
+            }
+        }
+        panic!("the error should have been reported");
+    }
+
+    #[test]
+    fn empty_set_nested_any_elements() {
+        let code = r#"
+            EMPTY_SET vector<any>
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::EmptySetError(String::from(
+                            "Set elements can't contain values of type any, they must be ordered"
+                        ))
+                    );
+                    return;
+                }
+            }
+        }
+        panic!("the error should have been reported");
+    }
+
+    #[test]
+    fn negative_sub() {
+        let code = r#"
+            PUSH 25
+            PUSH 20
+            SUB
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![StackEl::new(StackElValue::Int(-5))];
+        assert_eq!(lex.extras.stack.len(), 1);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn negative_literals() {
+        let code = r#"
+            PUSH -3
+            PUSH_INT -4
+            MUL
+            PUSH [-1, 2]
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![
+            StackEl::new(StackElValue::Vector(LifoVector::from_elements(
+                Type::Int,
+                vec![StackElValue::Int(-1), StackElValue::Int(2)],
+            ))),
+            StackEl::new(StackElValue::Int(12)),
+        ];
+        assert_eq!(lex.extras.stack.len(), 2);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn parse_negative_int() {
+        let code = r#"
+            PUSH "-42"
+            PARSE_INT
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![StackEl::new(StackElValue::Int(-42))];
+        assert_eq!(lex.extras.stack.len(), 1);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn add_overflow() {
+        let code = r#"
+            PUSH 170141183460469231731687303715884105727
+            PUSH 1
+            ADD
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::AddError(String::from(
+                            "Addition overflow"
+                        ))
+                    );
+                    return;
+                }
+            }
+        }
+        panic!("the error should have been reported");
+    }
+
+    #[test]
+    fn mul_overflow() {
+        let code = r#"
+            PUSH 170141183460469231731687303715884105727
+            PUSH 2
+            MUL
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::AddError(String::from(
+                            "Multiplication overflow"
+                        ))
+                    );
+                    return;
+                }
+            }
+        }
+        panic!("the error should have been reported");
+    }
+
+    #[test]
+    fn int_literal_too_long() {
+        let code = r#"
+            PUSH 1701411834604692317316873037158841057270
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::InvalidInteger(String::from(
+                            "Integer literal 1701411834604692317316873037158841057270 doesn't fit in an int"
+                        ))
+                    );
+                    return;
+                }
+            }
+        }
+        panic!("the error should have been reported");
+    }
+
+    #[test]
+    fn vector_int_literal_too_long() {
+        let code = r#"
+            PUSH [-1701411834604692317316873037158841057280]
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::InvalidInteger(String::from(
+                            "Integer literal -1701411834604692317316873037158841057280 doesn't fit in an int"
+                        ))
+                    );
+                    return;
+                }
+            }
+        }
+        panic!("the error should have been reported");
+    }
+
+    #[test]
+    fn negative_int_argument() {
+        let code = r#"
+            EMPTY_VECTOR
+            PUSH 1
+            INSERT
+            REMOVE -1
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::InvalidInteger(String::from(
+                            "-1"
+                        ))
+                    );
+                    return;
+                }
+            }
+        }
+        panic!("the error should have been reported");
+    }
+
+    #[test]
+    fn deeply_nested_quotations() {
+        let code = r#"
+            PUSH 41 { { { { { { { { { { { { { { { { { { { { { { { { { { { { { { { { { { { { { { { { PUSH 1 ADD } EXEC } EXEC } EXEC } EXEC } EXEC } EXEC } EXEC } EXEC } EXEC } EXEC } EXEC } EXEC } EXEC } EXEC } EXEC } EXEC } EXEC } EXEC } EXEC } EXEC } EXEC } EXEC } EXEC } EXEC } EXEC } EXEC } EXEC } EXEC } EXEC } EXEC } EXEC } EXEC } EXEC } EXEC } EXEC } EXEC } EXEC } EXEC } EXEC } EXEC
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        let expected_stack = vec![StackEl::new(StackElValue::Int(42))];
+        assert_eq!(lex.extras.stack.len(), 1);
+        assert_eq!(lex.extras.stack, expected_stack);
+    }
+
+    #[test]
+    fn invalid_block_in_vector_quotation() {
+        let code = r#"
+            PUSH [{ IF }]
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (),
+                Err(err) => {
+                    assert_eq!(
+                        err,
+                        LexingError::InvalidQuotation(String::from(
+                            "IF without matching END at 0..2 in quotation `{ IF }`"
+                        ))
+                    );
+                    return;
+                }
+            }
+        }
+        panic!("the error should have been reported");
+    }
+
+    #[test]
+    fn log_rendering() {
+        let code = r#"
+            PUSH 1
+            PUSH [2]
+            { DUP MUL }
+        "#;
+        let mut lex = lexer::Token::lexer(code);
+        for token in lex.by_ref() {
+            match token {
+                Ok(_tk) => (), // println!("{:#?}", tk),
+                Err(err) => panic!("an error occurred: {:?}", err),
+            }
+        }
+
+        assert_eq!(
+            stack::render_stack(&lex.extras.stack),
+            "[{ DUP MUL } : quotation, [2] : vector<int>, 1 : int]"
+        );
+    }
+
     #[test]
     fn final_test() {
         let code = r#"
@@ -898,7 +5762,7 @@ mod tests {
         "#;
 
         let mut lex = lexer::Token::lexer(code);
-        while let Some(token) = lex.next() {
+        for token in lex.by_ref() {
             match token {
                 Ok(_tk) => (), // println!("{:#?}", tk),
                 Err(err) => {
@@ -908,7 +5772,7 @@ mod tests {
             }
         }
 
-        let expected_stack = vec![StackEl::new(Token::Bool, StackElValue::Bool(true))];
+        let expected_stack = vec![StackEl::new(StackElValue::Bool(true))];
         // println!("stack: {:?}", lex.extras.stack);
         assert_eq!(lex.extras.stack.len(), 1);
         assert_eq!(lex.extras.stack, expected_stack);
@@ -0,0 +1,629 @@
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RegexError {
+    pub position: usize,
+    pub message: String,
+}
+impl RegexError {
+    fn new(position: usize, message: &str) -> Self {
+        RegexError {
+            position,
+            message: message.to_string(),
+        }
+    }
+}
+impl fmt::Display for RegexError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Invalid pattern: {} at position {}",
+            self.message, self.position
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum ClassItem {
+    Range(char, char),
+    Digit(bool),
+    Word(bool),
+    Space(bool),
+}
+impl ClassItem {
+    fn matches(&self, c: char) -> bool {
+        match self {
+            ClassItem::Range(start, end) => *start <= c && c <= *end,
+            ClassItem::Digit(negated) => c.is_ascii_digit() != *negated,
+            ClassItem::Word(negated) => (c.is_alphanumeric() || c == '_') != *negated,
+            ClassItem::Space(negated) => c.is_whitespace() != *negated,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Char(char),
+    Any,
+    Class(Vec<ClassItem>, bool),
+    Start,
+    End,
+    Group(Box<Node>, Option<usize>),
+    Concat(Vec<Node>),
+    Alternation(Vec<Node>),
+    Repeat {
+        node: Box<Node>,
+        min: usize,
+        max: Option<usize>,
+        greedy: bool,
+    },
+}
+
+// start and end (in chars) of every capture group, group 0 being the whole match
+pub type Captures = Vec<Option<(usize, usize)>>;
+
+// repetitions are compiled by copying the repeated pattern, this bounds the size of the program
+const MAX_PROGRAM_LEN: usize = 10_000;
+// bounds the work done by a single match, replacement included
+const MAX_STEPS: usize = 10_000_000;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StepLimitError;
+impl fmt::Display for StepLimitError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Matching stopped after {} steps, the input is too long for this pattern",
+            MAX_STEPS
+        )
+    }
+}
+
+// number of instructions the node compiles to
+fn program_len(node: &Node) -> usize {
+    match node {
+        Node::Char(_) | Node::Any | Node::Class(_, _) | Node::Start | Node::End => 1,
+        Node::Group(inner, None) => program_len(inner),
+        Node::Group(inner, Some(_)) => program_len(inner).saturating_add(2),
+        Node::Concat(nodes) => nodes
+            .iter()
+            .fold(0, |len, node| len.saturating_add(program_len(node))),
+        Node::Alternation(branches) => {
+            branches.iter().fold(2 * (branches.len() - 1), |len, node| {
+                len.saturating_add(program_len(node))
+            })
+        }
+        Node::Repeat { node, min, max, .. } => {
+            let len = program_len(node);
+            let optional = match max {
+                None => len.saturating_add(2),
+                Some(max) => len.saturating_add(1).saturating_mul(max - min),
+            };
+            len.saturating_mul(*min).saturating_add(optional)
+        }
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    groups: usize,
+}
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn parse_alternation(&mut self) -> Result<Node, RegexError> {
+        let mut branches = vec![self.parse_concat()?];
+        while self.peek() == Some('|') {
+            self.pos += 1;
+            branches.push(self.parse_concat()?);
+        }
+
+        if branches.len() == 1 {
+            Ok(branches.remove(0))
+        } else {
+            Ok(Node::Alternation(branches))
+        }
+    }
+
+    fn parse_concat(&mut self) -> Result<Node, RegexError> {
+        let mut nodes = vec![];
+        while let Some(c) = self.peek() {
+            if c == '|' || c == ')' {
+                break;
+            }
+            let atom = self.parse_atom()?;
+            nodes.push(self.parse_quantifier(atom)?);
+        }
+
+        Ok(Node::Concat(nodes))
+    }
+
+    fn parse_atom(&mut self) -> Result<Node, RegexError> {
+        let start = self.pos;
+        let c = self.peek().unwrap();
+        self.pos += 1;
+        match c {
+            '(' => {
+                let index = if self.chars[self.pos..].starts_with(&['?', ':']) {
+                    self.pos += 2;
+                    None
+                } else {
+                    self.groups += 1;
+                    Some(self.groups)
+                };
+                let inner = self.parse_alternation()?;
+                if self.peek() != Some(')') {
+                    return Err(RegexError::new(start, "missing closing parenthesis"));
+                }
+                self.pos += 1;
+                Ok(Node::Group(Box::new(inner), index))
+            }
+            '[' => self.parse_class(start),
+            '.' => Ok(Node::Any),
+            '^' => Ok(Node::Start),
+            '$' => Ok(Node::End),
+            '\\' => self.parse_escape(start),
+            '*' | '+' | '?' => Err(RegexError::new(start, "nothing to repeat")),
+            '{' if self.quantifier_bounds().is_some() => {
+                Err(RegexError::new(start, "nothing to repeat"))
+            }
+            _ => Ok(Node::Char(c)),
+        }
+    }
+
+    fn parse_escape(&mut self, start: usize) -> Result<Node, RegexError> {
+        match self.escaped_class_item(start)? {
+            Ok(item) => Ok(Node::Class(vec![item], false)),
+            Err(c) => Ok(Node::Char(c)),
+        }
+    }
+
+    // parses the character following a backslash, which is either a shorthand class or a literal
+    fn escaped_class_item(&mut self, start: usize) -> Result<Result<ClassItem, char>, RegexError> {
+        let c = match self.peek() {
+            None => return Err(RegexError::new(start, "trailing backslash")),
+            Some(c) => c,
+        };
+        self.pos += 1;
+        match c {
+            'd' => Ok(Ok(ClassItem::Digit(false))),
+            'D' => Ok(Ok(ClassItem::Digit(true))),
+            'w' => Ok(Ok(ClassItem::Word(false))),
+            'W' => Ok(Ok(ClassItem::Word(true))),
+            's' => Ok(Ok(ClassItem::Space(false))),
+            'S' => Ok(Ok(ClassItem::Space(true))),
+            'n' => Ok(Err('\n')),
+            't' => Ok(Err('\t')),
+            'r' => Ok(Err('\r')),
+            c if c.is_alphanumeric() => Err(RegexError::new(start, "unknown escape sequence")),
+            c => Ok(Err(c)),
+        }
+    }
+
+    fn parse_class(&mut self, start: usize) -> Result<Node, RegexError> {
+        let negated = self.peek() == Some('^');
+        if negated {
+            self.pos += 1;
+        }
+
+        let mut items = vec![];
+        let mut first = true;
+        loop {
+            let item_start = self.pos;
+            let c = match self.peek() {
+                None => return Err(RegexError::new(start, "unterminated character class")),
+                Some(c) => c,
+            };
+            self.pos += 1;
+            if c == ']' && !first {
+                break;
+            }
+            first = false;
+
+            let low = if c == '\\' {
+                match self.escaped_class_item(item_start)? {
+                    Ok(item) => {
+                        items.push(item);
+                        continue;
+                    }
+                    Err(c) => c,
+                }
+            } else {
+                c
+            };
+
+            // a dash right before the closing bracket is a literal
+            if self.peek() == Some('-') && self.chars.get(self.pos + 1).is_some_and(|c| *c != ']') {
+                self.pos += 1;
+                let high_start = self.pos;
+                let mut high = self.chars[self.pos];
+                self.pos += 1;
+                if high == '\\' {
+                    match self.escaped_class_item(high_start)? {
+                        Ok(_) => return Err(RegexError::new(high_start, "invalid range")),
+                        Err(c) => high = c,
+                    }
+                }
+                if high < low {
+                    return Err(RegexError::new(item_start, "invalid range"));
+                }
+                items.push(ClassItem::Range(low, high));
+            } else {
+                items.push(ClassItem::Range(low, low));
+            }
+        }
+
+        Ok(Node::Class(items, negated))
+    }
+
+    // reads `{n}`, `{n,}` or `{n,m}` at the current position without consuming it
+    fn quantifier_bounds(&self) -> Option<(usize, Option<usize>, usize)> {
+        let rest: String = self.chars[self.pos..].iter().collect();
+        let close = rest.find('}')?;
+        let body = &rest[..close];
+        let parsed = match body.split_once(',') {
+            None => {
+                let n = body.parse().ok()?;
+                (n, Some(n))
+            }
+            Some((min, "")) => (min.parse().ok()?, None),
+            Some((min, max)) => (min.parse().ok()?, Some(max.parse().ok()?)),
+        };
+
+        Some((parsed.0, parsed.1, body.chars().count() + 1))
+    }
+
+    fn parse_quantifier(&mut self, atom: Node) -> Result<Node, RegexError> {
+        let start = self.pos;
+        let (min, max) = match self.peek() {
+            Some('*') => {
+                self.pos += 1;
+                (0, None)
+            }
+            Some('+') => {
+                self.pos += 1;
+                (1, None)
+            }
+            Some('?') => {
+                self.pos += 1;
+                (0, Some(1))
+            }
+            Some('{') => {
+                self.pos += 1;
+                match self.quantifier_bounds() {
+                    None => {
+                        // not a quantifier, the brace is a literal character
+                        self.pos -= 1;
+                        return Ok(atom);
+                    }
+                    Some((min, max, len)) => {
+                        if max.is_some_and(|max| max < min) {
+                            return Err(RegexError::new(start, "invalid repetition range"));
+                        }
+                        self.pos += len;
+                        (min, max)
+                    }
+                }
+            }
+            _ => return Ok(atom),
+        };
+
+        let greedy = if self.peek() == Some('?') {
+            self.pos += 1;
+            false
+        } else {
+            true
+        };
+
+        let node = Node::Repeat {
+            node: Box::new(atom),
+            min,
+            max,
+            greedy,
+        };
+        if program_len(&node) > MAX_PROGRAM_LEN {
+            return Err(RegexError::new(start, "repetition too large"));
+        }
+
+        Ok(node)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Inst {
+    Char(char),
+    Any,
+    Class(Vec<ClassItem>, bool),
+    Start,
+    End,
+    // records the current position in a capture slot, group n uses the slots 2n and 2n + 1
+    Save(usize),
+    // follows both branches, the first one having the priority
+    Split(usize, usize),
+    Jump(usize),
+    Match,
+}
+
+fn compile(node: &Node, insts: &mut Vec<Inst>) {
+    match node {
+        Node::Char(c) => insts.push(Inst::Char(*c)),
+        Node::Any => insts.push(Inst::Any),
+        Node::Class(items, negated) => insts.push(Inst::Class(items.clone(), *negated)),
+        Node::Start => insts.push(Inst::Start),
+        Node::End => insts.push(Inst::End),
+        Node::Group(inner, None) => compile(inner, insts),
+        Node::Group(inner, Some(index)) => {
+            insts.push(Inst::Save(2 * index));
+            compile(inner, insts);
+            insts.push(Inst::Save(2 * index + 1));
+        }
+        Node::Concat(nodes) => nodes.iter().for_each(|node| compile(node, insts)),
+        Node::Alternation(branches) => {
+            let mut jumps = vec![];
+            for (i, branch) in branches.iter().enumerate() {
+                if i == branches.len() - 1 {
+                    compile(branch, insts);
+                    break;
+                }
+                // the placeholders are patched once the targets are known
+                let split = insts.len();
+                insts.push(Inst::Jump(0));
+                compile(branch, insts);
+                jumps.push(insts.len());
+                insts.push(Inst::Jump(0));
+                insts[split] = Inst::Split(split + 1, insts.len());
+            }
+            let end = insts.len();
+            for jump in jumps {
+                insts[jump] = Inst::Jump(end);
+            }
+        }
+        Node::Repeat {
+            node,
+            min,
+            max,
+            greedy,
+        } => {
+            let branch = |body: usize, skip: usize| match greedy {
+                true => Inst::Split(body, skip),
+                false => Inst::Split(skip, body),
+            };
+            (0..*min).for_each(|_| compile(node, insts));
+            match max {
+                None => {
+                    let split = insts.len();
+                    insts.push(Inst::Jump(0));
+                    compile(node, insts);
+                    insts.push(Inst::Jump(split));
+                    insts[split] = branch(split + 1, insts.len());
+                }
+                Some(max) => {
+                    let mut splits = vec![];
+                    for _ in *min..*max {
+                        splits.push(insts.len());
+                        insts.push(Inst::Jump(0));
+                        compile(node, insts);
+                    }
+                    let end = insts.len();
+                    for split in splits {
+                        insts[split] = branch(split + 1, end);
+                    }
+                }
+            }
+        }
+    }
+}
+
+// a thread of the matcher: its instruction and its capture slots
+type Thread = (usize, Vec<Option<usize>>);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Regex {
+    insts: Vec<Inst>,
+    groups: usize,
+}
+impl Regex {
+    pub fn new(pattern: &str) -> Result<Self, RegexError> {
+        let mut parser = Parser {
+            chars: pattern.chars().collect(),
+            pos: 0,
+            groups: 0,
+        };
+        let root = parser.parse_alternation()?;
+        if parser.pos < parser.chars.len() {
+            // the only way to stop early is an unmatched `)`
+            return Err(RegexError::new(parser.pos, "unmatched closing parenthesis"));
+        }
+        if program_len(&root) > MAX_PROGRAM_LEN {
+            return Err(RegexError::new(0, "pattern too large"));
+        }
+
+        // the whole match is the group 0
+        let mut insts = vec![Inst::Save(0)];
+        compile(&root, &mut insts);
+        insts.push(Inst::Save(1));
+        insts.push(Inst::Match);
+
+        Ok(Regex {
+            insts,
+            groups: parser.groups,
+        })
+    }
+
+    pub fn is_match(&self, input: &str) -> Result<bool, StepLimitError> {
+        let chars: Vec<char> = input.chars().collect();
+
+        let mut steps = MAX_STEPS;
+
+        Ok(self.captures_at(&chars, 0, &mut steps)?.is_some())
+    }
+
+    pub fn captures(&self, input: &str) -> Result<Option<Vec<String>>, StepLimitError> {
+        let chars: Vec<char> = input.chars().collect();
+        let mut steps = MAX_STEPS;
+        let caps = match self.captures_at(&chars, 0, &mut steps)? {
+            None => return Ok(None),
+            Some(caps) => caps,
+        };
+
+        Ok(Some(
+            caps.iter()
+                .map(|cap| match cap {
+                    None => String::new(),
+                    Some((start, end)) => chars[*start..*end].iter().collect(),
+                })
+                .collect(),
+        ))
+    }
+
+    // replaces every match, `$n` in the replacement refers to the group n and `$$` is a dollar sign
+    pub fn replace_all(&self, input: &str, replacement: &str) -> Result<String, StepLimitError> {
+        let chars: Vec<char> = input.chars().collect();
+        let mut steps = MAX_STEPS;
+        let mut output = String::new();
+        let mut pos = 0;
+        while pos <= chars.len() {
+            let caps = match self.captures_at(&chars, pos, &mut steps)? {
+                None => break,
+                Some(caps) => caps,
+            };
+            let (start, end) = caps[0].unwrap();
+            output.extend(&chars[pos..start]);
+            self.expand(&chars, &caps, replacement, &mut output);
+            if end == start {
+                // empty match, moves forward to avoid matching at the same spot forever
+                if let Some(c) = chars.get(end) {
+                    output.push(*c);
+                }
+                pos = end + 1;
+            } else {
+                pos = end;
+            }
+        }
+        if pos < chars.len() {
+            output.extend(&chars[pos..]);
+        }
+
+        Ok(output)
+    }
+
+    fn expand(&self, chars: &[char], caps: &Captures, replacement: &str, output: &mut String) {
+        let mut repl = replacement.chars().peekable();
+        while let Some(c) = repl.next() {
+            if c != '$' {
+                output.push(c);
+                continue;
+            }
+            match repl.peek() {
+                Some('$') => {
+                    repl.next();
+                    output.push('$');
+                }
+                Some(d) if d.is_ascii_digit() => {
+                    let group = d.to_digit(10).unwrap() as usize;
+                    repl.next();
+                    if let Some(Some((start, end))) = caps.get(group) {
+                        output.extend(&chars[*start..*end]);
+                    }
+                }
+                _ => output.push('$'),
+            }
+        }
+    }
+
+    // runs the threads of all the starting positions side by side (Pike VM), in priority order,
+    // so that the leftmost match is found with the same preferences as a backtracking matcher
+    fn captures_at(
+        &self,
+        chars: &[char],
+        from: usize,
+        steps: &mut usize,
+    ) -> Result<Option<Captures>, StepLimitError> {
+        // the position at which every instruction was last added, so that no thread is added twice
+        let mut added = vec![usize::MAX; self.insts.len()];
+        let mut threads: Vec<Thread> = vec![];
+        let mut found = None;
+        for pos in from..=chars.len() {
+            if found.is_none() {
+                // a match starting here has a lower priority than the ones started before
+                let slots = vec![None; 2 * (self.groups + 1)];
+                self.add_thread(&mut threads, &mut added, (0, slots), chars, pos, steps)?;
+            }
+
+            let mut next_threads = vec![];
+            for (pc, slots) in threads {
+                let consumed = match (&self.insts[pc], chars.get(pos)) {
+                    (Inst::Match, _) => {
+                        // the threads left have a lower priority than this match
+                        found = Some(slots);
+                        break;
+                    }
+                    (Inst::Char(expected), Some(c)) => expected == c,
+                    (Inst::Any, Some(c)) => *c != '\n',
+                    (Inst::Class(items, negated), Some(c)) => {
+                        items.iter().any(|item| item.matches(*c)) != *negated
+                    }
+                    _ => false,
+                };
+                if consumed {
+                    let thread = (pc + 1, slots);
+                    self.add_thread(&mut next_threads, &mut added, thread, chars, pos + 1, steps)?;
+                }
+            }
+            threads = next_threads;
+            if threads.is_empty() && found.is_some() {
+                break;
+            }
+        }
+
+        Ok(found.map(|slots| {
+            (0..=self.groups)
+                .map(|group| match (slots[2 * group], slots[2 * group + 1]) {
+                    (Some(start), Some(end)) => Some((start, end)),
+                    _ => None,
+                })
+                .collect()
+        }))
+    }
+
+    // follows the instructions that don't consume any char, depth first so that priorities are kept
+    fn add_thread(
+        &self,
+        threads: &mut Vec<Thread>,
+        added: &mut [usize],
+        thread: Thread,
+        chars: &[char],
+        pos: usize,
+        steps: &mut usize,
+    ) -> Result<(), StepLimitError> {
+        let mut pending = vec![thread];
+        while let Some((pc, mut slots)) = pending.pop() {
+            if added[pc] == pos {
+                continue;
+            }
+            added[pc] = pos;
+            *steps = steps.checked_sub(1).ok_or(StepLimitError)?;
+
+            match &self.insts[pc] {
+                Inst::Jump(target) => pending.push((*target, slots)),
+                Inst::Split(first, second) => {
+                    pending.push((*second, slots.clone()));
+                    pending.push((*first, slots));
+                }
+                Inst::Save(slot) => {
+                    slots[*slot] = Some(pos);
+                    pending.push((pc + 1, slots));
+                }
+                Inst::Start if pos == 0 => pending.push((pc + 1, slots)),
+                Inst::End if pos == chars.len() => pending.push((pc + 1, slots)),
+                Inst::Start | Inst::End => (),
+                _ => threads.push((pc, slots)),
+            }
+        }
+
+        Ok(())
+    }
+}
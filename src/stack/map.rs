@@ -0,0 +1,115 @@
+use super::{natural_order, render_element, sorted_search, StackElValue, Type};
+use std::fmt;
+
+// entries are kept sorted by key so that maps are always printed in the same order
+#[derive(Debug, Clone, PartialEq)]
+pub struct LifoMap {
+    pub key_type: Type,
+    pub value_type: Type,
+    pub entries: Vec<(StackElValue, StackElValue)>,
+}
+impl LifoMap {
+    pub fn new() -> Self {
+        LifoMap::of(Type::Unknown, Type::Unknown)
+    }
+
+    pub fn of(key_type: Type, value_type: Type) -> Self {
+        LifoMap {
+            key_type,
+            value_type,
+            entries: vec![],
+        }
+    }
+
+    fn map_type(&self) -> Type {
+        Type::Map(
+            Box::new(self.key_type.clone()),
+            Box::new(self.value_type.clone()),
+        )
+    }
+
+    // maps of unknown type take the types of the first entry
+    fn check_key(&self, key: &StackElValue) -> Result<Type, String> {
+        // only values with a natural ordering can be sorted, and values nested in an `any`
+        // collection may not be comparable with the ones of the other keys
+        if natural_order(key, key).is_none() || key.get_type().contains_any() {
+            return Err(format!(
+                "Cannot use value of type {} as a map key",
+                key.type_name()
+            ));
+        }
+
+        self.key_type.unify(&key.get_type()).ok_or(format!(
+            "Cannot use key of type {} in map of type {}",
+            key.type_name(),
+            self.map_type()
+        ))
+    }
+
+    fn check_value(&self, value: &StackElValue) -> Result<Type, String> {
+        if self.value_type == Type::Any {
+            return Ok(Type::Any);
+        }
+
+        self.value_type.unify(&value.get_type()).ok_or(format!(
+            "Cannot insert value of type {} into map of type {}",
+            value.type_name(),
+            self.map_type()
+        ))
+    }
+
+    fn search(&self, key: &StackElValue) -> Result<usize, usize> {
+        sorted_search(&self.entries, key, |(k, _)| k)
+    }
+
+    pub fn put(self, key: StackElValue, value: StackElValue) -> Result<Self, String> {
+        let key_type = self.check_key(&key)?;
+        let value_type = self.check_value(&value)?;
+        let position = self.search(&key);
+        let mut entries = self.entries;
+        match position {
+            Ok(i) => entries[i] = (key, value),
+            Err(i) => entries.insert(i, (key, value)),
+        }
+
+        Ok(LifoMap {
+            key_type,
+            value_type,
+            entries,
+        })
+    }
+
+    pub fn get(&self, key: &StackElValue) -> Result<Option<&StackElValue>, String> {
+        self.check_key(key)?;
+
+        Ok(self.search(key).ok().map(|i| &self.entries[i].1))
+    }
+
+    // removing a missing key leaves the map unchanged
+    pub fn remove(self, key: &StackElValue) -> Result<Self, String> {
+        self.check_key(key)?;
+        let position = self.search(key);
+        let mut entries = self.entries;
+        if let Ok(i) = position {
+            entries.remove(i);
+        }
+
+        Ok(LifoMap { entries, ..self })
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+}
+
+impl fmt::Display for LifoMap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let entries: Vec<String> = self
+            .entries
+            .iter()
+            .map(|(key, value)| format!("{}: {}", render_element(key), render_element(value)))
+            .collect();
+
+        write!(f, "{{{}}}", entries.join(", "))
+    }
+}
@@ -1,89 +1,222 @@
-use crate::lexer::Token;
+use crate::regex::Regex;
+use std::cmp::Ordering;
+use std::fmt;
+
+mod map;
+mod record;
+mod set;
+mod types;
+pub use map::LifoMap;
+pub use record::LifoRecord;
+pub use set::LifoSet;
+pub use types::Type;
 
 #[derive(Debug, Clone, PartialEq)]
-pub enum LifoVector {
-    EmptyVector,
-    VectorOfInt(Vec<usize>),
-    VectorOfBool(Vec<bool>),
-    VectorOfString(Vec<String>),
+pub struct LifoVector {
+    pub element_type: Type,
+    pub elements: Vec<StackElValue>,
 }
 impl LifoVector {
     pub fn new() -> Self {
-        return LifoVector::EmptyVector;
+        LifoVector::of(Type::Unknown)
     }
 
-    pub fn insert(self, el: StackElValue) -> Result<Self, String> {
-        match (self.clone(), el.clone()) {
-            // vector is empty
-            (LifoVector::EmptyVector, StackElValue::Int(val)) => {
-                Ok(LifoVector::VectorOfInt(vec![val]))
-            }
-            (LifoVector::EmptyVector, StackElValue::Bool(val)) => {
-                Ok(LifoVector::VectorOfBool(vec![val]))
-            }
-            (LifoVector::EmptyVector, StackElValue::String(val)) => {
-                Ok(LifoVector::VectorOfString(vec![val]))
-            }
-            // vector is already populated
-            (LifoVector::VectorOfBool(mut vector), StackElValue::Bool(val)) => {
-                vector.push(val);
-                Ok(LifoVector::VectorOfBool(vector))
-            }
-            (LifoVector::VectorOfInt(mut vector), StackElValue::Int(val)) => {
-                vector.push(val);
-                Ok(LifoVector::VectorOfInt(vector))
-            }
-            (LifoVector::VectorOfString(mut vector), StackElValue::String(val)) => {
-                vector.push(val);
-                Ok(LifoVector::VectorOfString(vector))
-            }
-            _ => {
-                let vector_type = match self {
-                    LifoVector::EmptyVector => "unknown",
-                    LifoVector::VectorOfBool(_) => "bool",
-                    LifoVector::VectorOfInt(_) => "int",
-                    LifoVector::VectorOfString(_) => "string",
-                };
-
-                Err(format!(
-                    "Cannot insert value of type {} into vector of type vector<{}>",
-                    el.to_string(),
-                    vector_type
-                ))
-            }
+    pub fn of(element_type: Type) -> Self {
+        LifoVector {
+            element_type,
+            elements: vec![],
+        }
+    }
+
+    pub fn from_elements(element_type: Type, elements: Vec<StackElValue>) -> Self {
+        LifoVector {
+            element_type,
+            elements,
+        }
+    }
+
+    // vectors of unknown type take the type of the first element inserted
+    fn check_element(&self, el: &StackElValue) -> Result<Type, String> {
+        let new_type = if self.element_type == Type::Any {
+            Some(Type::Any)
+        } else {
+            self.element_type.unify(&el.get_type())
+        };
+
+        new_type.ok_or(format!(
+            "Cannot insert value of type {} into vector of type vector<{}>",
+            el.type_name(),
+            self.element_type
+        ))
+    }
+
+    fn check_index(&self, index: usize, len: usize) -> Result<(), String> {
+        if index >= len {
+            return Err(format!(
+                "Out of bound index {} for vector of length {}",
+                index,
+                self.len()
+            ));
         }
+
+        Ok(())
+    }
+
+    pub fn insert(self, el: StackElValue) -> Result<Self, String> {
+        let new_type = self.check_element(&el)?;
+        let mut elements = self.elements;
+        elements.push(el);
+
+        Ok(LifoVector::from_elements(new_type, elements))
+    }
+
+    pub fn insert_at(self, index: usize, el: StackElValue) -> Result<Self, String> {
+        let new_type = self.check_element(&el)?;
+        // inserting right after the last element is allowed
+        self.check_index(index, self.len() + 1)?;
+        let mut elements = self.elements;
+        elements.insert(index, el);
+
+        Ok(LifoVector::from_elements(new_type, elements))
+    }
+
+    pub fn set(self, index: usize, el: StackElValue) -> Result<Self, String> {
+        let new_type = self.check_element(&el)?;
+        self.check_index(index, self.len())?;
+        let mut elements = self.elements;
+        elements[index] = el;
+
+        Ok(LifoVector::from_elements(new_type, elements))
+    }
+
+    pub fn remove(self, index: usize) -> Result<(Self, StackElValue), String> {
+        self.check_index(index, self.len())?;
+        let mut elements = self.elements;
+        let removed = elements.remove(index);
+
+        Ok((LifoVector::from_elements(self.element_type, elements), removed))
+    }
+
+    pub fn len(&self) -> usize {
+        self.elements.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.elements.is_empty()
+    }
+}
+
+impl fmt::Display for LifoVector {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let elements: Vec<String> = self.elements.iter().map(render_element).collect();
+
+        write!(f, "[{}]", elements.join(", "))
+    }
+}
+
+// strings and chars are quoted inside collections so that elements containing commas stay readable
+fn render_element(el: &StackElValue) -> String {
+    match el {
+        StackElValue::String(val) => format!("{:?}", val),
+        StackElValue::Char(val) => format!("{:?}", val),
+        _ => el.to_string(),
     }
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum StackElValue {
-    Int(usize),
+    // signed and wide enough for every usize value, arithmetic errors instead of overflowing
+    Int(i128),
     String(String),
     Bool(bool),
+    Char(char),
+    Bytes(Vec<u8>),
     Vector(LifoVector),
+    Map(LifoMap),
+    Set(LifoSet),
+    Pair(Box<StackElValue>, Box<StackElValue>),
+    Record(LifoRecord),
+    // type of the value an option can hold, and the value if there is one
+    Option(Type, Option<Box<StackElValue>>),
+    // or values hold one side and the type of the other side
+    Left(Box<StackElValue>, Type),
+    Right(Type, Box<StackElValue>),
+    // code of a quotation literal, run by `EXEC`
+    Quotation(String),
 }
 impl StackElValue {
-    pub fn to_string(self) -> String {
+    pub fn get_type(&self) -> Type {
+        match self {
+            StackElValue::Bool(_) => Type::Bool,
+            StackElValue::Int(_) => Type::Int,
+            StackElValue::String(_) => Type::String,
+            StackElValue::Char(_) => Type::Char,
+            StackElValue::Bytes(_) => Type::Bytes,
+            StackElValue::Vector(val) => Type::Vector(Box::new(val.element_type.clone())),
+            StackElValue::Map(val) => Type::Map(
+                Box::new(val.key_type.clone()),
+                Box::new(val.value_type.clone()),
+            ),
+            StackElValue::Set(val) => Type::Set(Box::new(val.element_type.clone())),
+            StackElValue::Pair(first, second) => {
+                Type::Pair(Box::new(first.get_type()), Box::new(second.get_type()))
+            }
+            StackElValue::Record(val) => val.record_type(),
+            StackElValue::Option(el_type, _) => Type::Option(Box::new(el_type.clone())),
+            StackElValue::Left(val, right_type) => {
+                Type::Or(Box::new(val.get_type()), Box::new(right_type.clone()))
+            }
+            StackElValue::Right(left_type, val) => {
+                Type::Or(Box::new(left_type.clone()), Box::new(val.get_type()))
+            }
+            StackElValue::Quotation(_) => Type::Quotation,
+        }
+    }
+
+    pub fn type_name(&self) -> String {
+        self.get_type().to_string()
+    }
+}
+impl fmt::Display for StackElValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            StackElValue::Bool(_) => String::from("bool"),
-            StackElValue::Int(_) => String::from("int"),
-            StackElValue::String(_) => String::from("string"),
-            StackElValue::Vector(_) => String::from("vector"),
+            StackElValue::Bool(val) => write!(f, "{}", val),
+            StackElValue::Int(val) => write!(f, "{}", val),
+            StackElValue::String(val) => write!(f, "{}", val),
+            StackElValue::Char(val) => write!(f, "{}", val),
+            StackElValue::Bytes(val) => {
+                write!(f, "0x")?;
+                val.iter().try_for_each(|byte| write!(f, "{:02x}", byte))
+            }
+            StackElValue::Vector(val) => write!(f, "{}", val),
+            StackElValue::Map(val) => write!(f, "{}", val),
+            StackElValue::Set(val) => write!(f, "{}", val),
+            StackElValue::Pair(first, second) => {
+                write!(f, "({}, {})", render_element(first), render_element(second))
+            }
+            StackElValue::Record(val) => write!(f, "{}", val),
+            StackElValue::Option(_, Some(val)) => write!(f, "Some({})", render_element(val)),
+            StackElValue::Option(_, None) => write!(f, "None"),
+            StackElValue::Left(val, _) => write!(f, "Left({})", render_element(val)),
+            StackElValue::Right(_, val) => write!(f, "Right({})", render_element(val)),
+            StackElValue::Quotation(code) if code.is_empty() => write!(f, "{{ }}"),
+            StackElValue::Quotation(code) => write!(f, "{{ {} }}", code),
         }
     }
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct StackEl {
-    pub token: Token,
     pub value: StackElValue,
 }
 impl StackEl {
-    pub fn new(tk: Token, val: StackElValue) -> Self {
-        StackEl {
-            token: tk,
-            value: val,
-        }
+    pub fn new(val: StackElValue) -> Self {
+        StackEl { value: val }
+    }
+
+    // the type is always derived from the value so that it can't get out of sync
+    pub fn get_type(&self) -> Type {
+        self.value.get_type()
     }
 
     pub fn print(self) -> String {
@@ -91,33 +224,41 @@ impl StackEl {
             StackElValue::Bool(val) => format!("{} : bool", val),
             StackElValue::Int(val) => format!("{} : int", val),
             StackElValue::String(val) => format!("{} : string", val),
-            StackElValue::Vector(val) => format!("{:?} : vector", val),
+            StackElValue::Char(val) => format!("{} : char", val),
+            StackElValue::Bytes(_) => format!("{} : bytes", self.value),
+            StackElValue::Vector(val) => format!("{} : {}", val, self.value.type_name()),
+            StackElValue::Map(val) => format!("{} : {}", val, self.value.type_name()),
+            StackElValue::Set(val) => format!("{} : {}", val, self.value.type_name()),
+            StackElValue::Pair(_, _) => format!("{} : {}", self.value, self.value.type_name()),
+            StackElValue::Record(val) => format!("{} : {}", val, self.value.type_name()),
+            StackElValue::Option(_, _)
+            | StackElValue::Left(_, _)
+            | StackElValue::Right(_, _)
+            | StackElValue::Quotation(_) => format!("{} : {}", self.value, self.value.type_name()),
         }
     }
 }
 
 pub type Stack = Vec<StackEl>;
 
+// the stack as shown by `LOG`, from the top element down
+pub fn render_stack(stack: &Stack) -> String {
+    let elements: Vec<String> = stack.iter().map(|el| el.clone().print()).collect();
+    format!("[{}]", elements.join(", "))
+}
+
 pub fn add(stack: &Stack) -> Result<Stack, String> {
     if stack.len() < 2 {
         return Err(String::from("Stack must be at least 2 elements deep"));
     }
 
-    match (
-        &stack[0].token,
-        &stack[0].value,
-        &stack[1].token,
-        &stack[1].value,
-    ) {
-        (
-            Token::Int | Token::Index,
-            StackElValue::Int(val1),
-            Token::Int | Token::Index,
-            StackElValue::Int(val2),
-        ) => {
-            let new_value = val1 + val2;
-            let new_stack = vec![
-                vec![StackEl::new(Token::Int, StackElValue::Int(new_value))],
+    match (&stack[0].value, &stack[1].value) {
+        (StackElValue::Int(val1), StackElValue::Int(val2)) => {
+            let new_value = val1
+                .checked_add(*val2)
+                .ok_or(String::from("Addition overflow"))?;
+            let new_stack = [
+                vec![StackEl::new(StackElValue::Int(new_value))],
                 stack[2..].to_vec(),
             ]
             .concat();
@@ -133,20 +274,14 @@ pub fn sub(stack: &Stack) -> Result<Stack, String> {
         return Err(String::from("Stack must be at least 2 elements deep"));
     }
 
-    match (
-        &stack[0].token,
-        &stack[0].value,
-        &stack[1].token,
-        &stack[1].value,
-    ) {
-        (Token::Int, StackElValue::Int(minuend), Token::Int, StackElValue::Int(subtrahend)) => {
-            if minuend < subtrahend {
-                return Err(String::from("Subtraction overflow"));
-            }
-            let new_value = minuend - subtrahend;
+    match (&stack[0].value, &stack[1].value) {
+        (StackElValue::Int(minuend), StackElValue::Int(subtrahend)) => {
+            let new_value = minuend
+                .checked_sub(*subtrahend)
+                .ok_or(String::from("Subtraction overflow"))?;
 
-            let new_stack = vec![
-                vec![StackEl::new(Token::Int, StackElValue::Int(new_value))],
+            let new_stack = [
+                vec![StackEl::new(StackElValue::Int(new_value))],
                 stack[2..].to_vec(),
             ]
             .concat();
@@ -162,21 +297,13 @@ pub fn mul(stack: &Stack) -> Result<Stack, String> {
         return Err(String::from("Stack must be at least 2 elements deep"));
     }
 
-    match (
-        &stack[0].token,
-        &stack[0].value,
-        &stack[1].token,
-        &stack[1].value,
-    ) {
-        (
-            Token::Int,
-            StackElValue::Int(multiplicand),
-            Token::Int,
-            StackElValue::Int(multiplier),
-        ) => {
-            let new_value = multiplicand * multiplier;
-            let new_stack = vec![
-                vec![StackEl::new(Token::Int, StackElValue::Int(new_value))],
+    match (&stack[0].value, &stack[1].value) {
+        (StackElValue::Int(multiplicand), StackElValue::Int(multiplier)) => {
+            let new_value = multiplicand
+                .checked_mul(*multiplier)
+                .ok_or(String::from("Multiplication overflow"))?;
+            let new_stack = [
+                vec![StackEl::new(StackElValue::Int(new_value))],
                 stack[2..].to_vec(),
             ]
             .concat();
@@ -188,56 +315,73 @@ pub fn mul(stack: &Stack) -> Result<Stack, String> {
 }
 
 pub fn dup(stack: &Stack) -> Result<Stack, String> {
-    if stack.len() < 1 {
+    if stack.is_empty() {
         return Err(String::from("Stack must be at least 1 element deep"));
     }
 
     let new_value = stack[0].clone();
-    Ok(vec![vec![new_value], stack.clone()].concat())
+    Ok([vec![new_value], stack.clone()].concat())
 }
 
-pub fn eq(stack: &Stack) -> Result<Stack, String> {
+// `EQ` and `NEQ` accept values of compatible types and compare their structure,
+// so an empty vector of unknown type is equal to an empty vector of ints
+fn equal(stack: &Stack) -> Result<bool, String> {
     if stack.len() < 2 {
         return Err(String::from("Stack must be at least 2 elements deep"));
     }
 
-    if stack[0].value.clone().to_string() != stack[1].value.clone().to_string() {
+    if stack[0].get_type().unify(&stack[1].get_type()).is_none() {
         return Err(String::from("Elements must be of the same type"));
     }
 
-    let new_value = if stack[0].value == stack[1].value {
-        StackEl::new(Token::Bool, StackElValue::Bool(true))
-    } else {
-        StackEl::new(Token::Bool, StackElValue::Bool(false))
-    };
+    Ok(same_value(&stack[0].value, &stack[1].value))
+}
+
+// values that can't be ordered, like the elements of a vector of type `any`, are compared directly
+pub fn same_value(a: &StackElValue, b: &StackElValue) -> bool {
+    natural_order(a, b).map_or(a == b, |ordering| ordering == Ordering::Equal)
+}
 
-    let new_stack = vec![vec![new_value], stack[2..].to_vec()].concat();
+pub fn eq(stack: &Stack) -> Result<Stack, String> {
+    let new_value = StackEl::new(StackElValue::Bool(equal(stack)?));
+    let new_stack = [vec![new_value], stack[2..].to_vec()].concat();
 
     Ok(new_stack)
 }
 
 pub fn neq(stack: &Stack) -> Result<Stack, String> {
+    let new_value = StackEl::new(StackElValue::Bool(!equal(stack)?));
+    let new_stack = [vec![new_value], stack[2..].to_vec()].concat();
+
+    Ok(new_stack)
+}
+
+// -1 when the element on top of the stack is smaller, 0 when both are equal, 1 when it is greater
+pub fn compare(stack: &Stack) -> Result<Stack, String> {
     if stack.len() < 2 {
         return Err(String::from("Stack must be at least 2 elements deep"));
     }
 
-    if stack[0].token != stack[1].token {
-        return Err(String::from("Elements must be of the same type"));
+    if stack[0].get_type().unify(&stack[1].get_type()).is_none() {
+        return Err(format!(
+            "Cannot compare value of type {} with value of type {}",
+            stack[0].value.type_name(),
+            stack[1].value.type_name()
+        ));
     }
 
-    let new_value = if stack[0].value == stack[1].value {
-        StackEl::new(Token::Bool, StackElValue::Bool(false))
-    } else {
-        StackEl::new(Token::Bool, StackElValue::Bool(true))
-    };
-
-    let new_stack = vec![vec![new_value], stack[2..].to_vec()].concat();
+    let ordering = natural_order(&stack[0].value, &stack[1].value).ok_or(format!(
+        "Cannot compare values of type {}",
+        stack[0].value.type_name()
+    ))?;
+    let new_value = StackEl::new(StackElValue::Int(ordering as i128));
+    let new_stack = [vec![new_value], stack[2..].to_vec()].concat();
 
     Ok(new_stack)
 }
 
 pub fn pop(stack: &Stack) -> Result<Stack, String> {
-    if stack.len() < 1 {
+    if stack.is_empty() {
         return Err(String::from("Stack must be at least 1 element deep"));
     }
 
@@ -259,28 +403,25 @@ pub fn concat(stack: &mut Stack) -> Result<Stack, String> {
         return Err(String::from("Stack must be at least 2 elements deep"));
     }
 
-    match (
-        &stack[0].token,
-        &stack[0].value,
-        &stack[1].token,
-        &stack[1].value,
-    ) {
+    match (&stack[0].value, &stack[1].value) {
         (
-            Token::String | Token::Index,
-            StackElValue::String(val1),
-            Token::String | Token::Index,
-            StackElValue::String(val2),
+            val1 @ (StackElValue::String(_) | StackElValue::Char(_)),
+            val2 @ (StackElValue::String(_) | StackElValue::Char(_)),
         ) => {
             let new_value = format!("{}{}", val1, val2);
             let mut new_stack = stack[2..].to_vec();
-            new_stack.insert(
-                0,
-                StackEl::new(Token::String, StackElValue::String(new_value)),
-            );
+            new_stack.insert(0, StackEl::new(StackElValue::String(new_value)));
+
+            Ok(new_stack)
+        }
+        (StackElValue::Bytes(val1), StackElValue::Bytes(val2)) => {
+            let new_value = [val1.clone(), val2.clone()].concat();
+            let mut new_stack = stack[2..].to_vec();
+            new_stack.insert(0, StackEl::new(StackElValue::Bytes(new_value)));
 
             Ok(new_stack)
         }
-        _ => Err(String::from("Only strings can be concatenated")),
+        _ => Err(String::from("Only strings or bytes can be concatenated")),
     }
 }
 
@@ -294,8 +435,14 @@ pub fn insert_vector(stack: &mut Stack) -> Result<Stack, String> {
             let new_vector = lifo_vector.clone().insert(val_to_insert.clone())?;
             let stack_val = StackElValue::Vector(new_vector);
             // removes the 2 values on top of the stack and pushes the new vector
-            let new_stack = vec![
-                vec![StackEl::new(Token::InsertVector, stack_val)],
+            let new_stack = [vec![StackEl::new(stack_val)], stack[2..].to_vec()].concat();
+
+            Ok(new_stack)
+        }
+        (val_to_insert, StackElValue::Set(set)) => {
+            let new_set = set.clone().insert(val_to_insert.clone())?;
+            let new_stack = [
+                vec![StackEl::new(StackElValue::Set(new_set))],
                 stack[2..].to_vec(),
             ]
             .concat();
@@ -309,100 +456,1269 @@ pub fn insert_vector(stack: &mut Stack) -> Result<Stack, String> {
 }
 
 pub fn size(stack: &Stack) -> Result<Stack, String> {
-    if stack.len() < 1 {
+    if stack.is_empty() {
         return Err(String::from("Stack must be at least 1 element deep"));
     }
 
     let new_value = match stack[0].clone().value {
-        StackElValue::String(val) => Ok(StackEl::new(Token::Size, StackElValue::Int(val.len()))),
-        StackElValue::Vector(val) => {
-            let size = match val {
-                LifoVector::EmptyVector => 0,
-                LifoVector::VectorOfBool(vec) => vec.len(),
-                LifoVector::VectorOfInt(vec) => vec.len(),
-                LifoVector::VectorOfString(vec) => vec.len(),
-            };
-            Ok(StackEl::new(Token::Size, StackElValue::Int(size)))
+        // strings are indexed by chars, their size is their number of chars
+        StackElValue::String(val) => {
+            Ok(StackEl::new(StackElValue::Int(val.chars().count() as i128)))
         }
+        StackElValue::Bytes(val) => Ok(StackEl::new(StackElValue::Int(val.len() as i128))),
+        StackElValue::Vector(val) => Ok(StackEl::new(StackElValue::Int(val.len() as i128))),
+        StackElValue::Map(val) => Ok(StackEl::new(StackElValue::Int(val.len() as i128))),
+        StackElValue::Set(val) => Ok(StackEl::new(StackElValue::Int(val.len() as i128))),
         _ => Err(format!(
             "Cannot give the size of element of type {}",
-            stack[0].value.clone().to_string()
+            stack[0].value.type_name()
         )),
     }?;
 
-    let new_stack = vec![vec![new_value], stack[1..].to_vec()].concat();
+    let new_stack = [vec![new_value], stack[1..].to_vec()].concat();
 
     Ok(new_stack)
 }
 
-pub fn index(stack: &Stack, index: usize) -> Result<Stack, String> {
-    if stack.len() < 1 {
-        return Err(String::from("Stack must be at least 1 element deep"));
+// negative indexes count from the end, -1 being the last element
+fn resolve_index(index: isize, len: usize) -> Option<usize> {
+    let resolved = if index < 0 {
+        len.checked_sub(index.unsigned_abs())?
+    } else {
+        index as usize
+    };
+
+    if resolved < len {
+        Some(resolved)
+    } else {
+        None
     }
+}
 
-    let new_value = match stack[0].clone().value {
+// finds the element at the index with the type of the elements, the element is `None` when out of bounds
+fn element_at(value: &StackElValue, index: isize) -> Result<(Type, Option<StackElValue>), String> {
+    match value {
         StackElValue::String(val) => {
-            if val.len() < index {
-                Err(format!(
-                    "Out of bound index {} for string of length {}",
-                    index,
-                    val.len()
-                ))
-            } else {
-                Ok(StackEl::new(
-                    Token::Index,
-                    StackElValue::String(val.chars().nth(index).unwrap().to_string()),
-                ))
-            }
+            let chars: Vec<char> = val.chars().collect();
+            let el = resolve_index(index, chars.len()).map(|i| StackElValue::Char(chars[i]));
+            Ok((Type::Char, el))
         }
-        StackElValue::Vector(val) => match val {
-            LifoVector::EmptyVector => {
-                Err(format!("Out of bound index {} for empty vector", index))
-            }
-            LifoVector::VectorOfBool(vec) => {
-                if vec.len() < index {
-                    Err(format!(
-                        "Out of bound index {} for vector of length {}",
-                        index,
-                        vec.len()
-                    ))
-                } else {
-                    Ok(StackEl::new(Token::Index, StackElValue::Bool(vec[index])))
-                }
-            }
-            LifoVector::VectorOfInt(vec) => {
-                if vec.len() < index {
-                    Err(format!(
-                        "Out of bound index {} for vector of length {}",
-                        index,
-                        vec.len()
-                    ))
-                } else {
-                    Ok(StackEl::new(Token::Index, StackElValue::Int(vec[index])))
-                }
-            }
-            LifoVector::VectorOfString(vec) => {
-                if vec.len() < index {
-                    Err(format!(
-                        "Out of bound index {} for vector of length {}",
-                        index,
-                        vec.len()
-                    ))
-                } else {
-                    Ok(StackEl::new(
-                        Token::Index,
-                        StackElValue::String(vec[index].to_string()),
-                    ))
-                }
-            }
+        StackElValue::Bytes(val) => {
+            let el = resolve_index(index, val.len()).map(|i| StackElValue::Int(val[i] as i128));
+            Ok((Type::Int, el))
+        }
+        StackElValue::Vector(vec) => {
+            let el = resolve_index(index, vec.len()).map(|i| vec.elements[i].clone());
+            Ok((vec.element_type.clone(), el))
+        }
+        _ => Err(format!("Cannot index element of type {}", value.type_name())),
+    }
+}
+
+pub fn index(stack: &Stack, index: isize) -> Result<Stack, String> {
+    if stack.is_empty() {
+        return Err(String::from("Stack must be at least 1 element deep"));
+    }
+
+    let new_value = match element_at(&stack[0].value, index)? {
+        (_, Some(el)) => StackEl::new(el),
+        (_, None) => return Err(out_of_bounds(&stack[0].value, index)),
+    };
+
+    let new_stack = [vec![new_value], stack[1..].to_vec()].concat();
+
+    Ok(new_stack)
+}
+
+fn out_of_bounds(value: &StackElValue, index: impl fmt::Display) -> String {
+    match value {
+        StackElValue::String(val) => format!(
+            "Out of bound index {} for string of length {}",
+            index,
+            val.chars().count()
+        ),
+        StackElValue::Bytes(val) => format!(
+            "Out of bound index {} for bytes of length {}",
+            index,
+            val.len()
+        ),
+        StackElValue::Vector(vec) if vec.is_empty() => {
+            format!("Out of bound index {} for empty vector", index)
+        }
+        StackElValue::Vector(vec) => format!(
+            "Out of bound index {} for vector of length {}",
+            index,
+            vec.len()
+        ),
+        value => format!("Cannot index element of type {}", value.type_name()),
+    }
+}
+
+// like `index` but pushes an option that is none when the index is out of bounds
+pub fn safe_index(stack: &Stack, index: isize) -> Result<Stack, String> {
+    if stack.is_empty() {
+        return Err(String::from("Stack must be at least 1 element deep"));
+    }
+
+    let (el_type, el) = element_at(&stack[0].value, index)?;
+    let new_value = StackElValue::Option(el_type, el.map(Box::new));
+    let new_stack = [vec![StackEl::new(new_value)], stack[1..].to_vec()].concat();
+
+    Ok(new_stack)
+}
+
+fn stack_index(stack: &Stack) -> Result<i128, String> {
+    if stack.len() < 2 {
+        return Err(String::from("Stack must be at least 2 elements deep"));
+    }
+
+    match &stack[0].value {
+        StackElValue::Int(val) => Ok(*val),
+        _ => Err(format!(
+            "Index must be of type int, found {}",
+            stack[0].value.type_name()
+        )),
+    }
+}
+
+// takes the index from the top of the stack instead of the source code
+pub fn dynamic_index(stack: &Stack) -> Result<Stack, String> {
+    let val = stack_index(stack)?;
+    let rest = stack[1..].to_vec();
+    match isize::try_from(val) {
+        Ok(val) => index(&rest, val),
+        Err(_) => Err(out_of_bounds(&rest[0].value, val)),
+    }
+}
+
+pub fn dynamic_safe_index(stack: &Stack) -> Result<Stack, String> {
+    let val = stack_index(stack)?;
+    // no value is long enough to be indexed past `isize::MAX`
+    safe_index(&stack[1..].to_vec(), isize::try_from(val).unwrap_or(isize::MAX))
+}
+
+pub fn regex_match(stack: &Stack) -> Result<Stack, String> {
+    if stack.len() < 2 {
+        return Err(String::from("Stack must be at least 2 elements deep"));
+    }
+
+    match (&stack[0].value, &stack[1].value) {
+        (StackElValue::String(pattern), StackElValue::String(input)) => {
+            let regex = Regex::new(pattern).map_err(|err| err.to_string())?;
+            let new_value = StackEl::new(StackElValue::Bool(regex.is_match(input).map_err(|err| err.to_string())?));
+            let new_stack = [vec![new_value], stack[2..].to_vec()].concat();
+
+            Ok(new_stack)
+        }
+        _ => Err(String::from("Only strings can be matched against a pattern")),
+    }
+}
+
+pub fn capture(stack: &Stack) -> Result<Stack, String> {
+    if stack.len() < 2 {
+        return Err(String::from("Stack must be at least 2 elements deep"));
+    }
+
+    match (&stack[0].value, &stack[1].value) {
+        (StackElValue::String(pattern), StackElValue::String(input)) => {
+            let regex = Regex::new(pattern).map_err(|err| err.to_string())?;
+            // the whole match comes first, followed by the groups
+            // an empty vector means that the pattern didn't match
+            let groups = match regex.captures(input).map_err(|err| err.to_string())? {
+                None => vec![],
+                Some(groups) => groups.into_iter().map(StackElValue::String).collect(),
+            };
+            let groups = LifoVector::from_elements(Type::String, groups);
+            let new_value = StackEl::new(StackElValue::Vector(groups));
+            let new_stack = [vec![new_value], stack[2..].to_vec()].concat();
+
+            Ok(new_stack)
+        }
+        _ => Err(String::from("Only strings can be matched against a pattern")),
+    }
+}
+
+pub fn regex_replace(stack: &Stack) -> Result<Stack, String> {
+    if stack.len() < 3 {
+        return Err(String::from("Stack must be at least 3 elements deep"));
+    }
+
+    match (&stack[0].value, &stack[1].value, &stack[2].value) {
+        (
+            StackElValue::String(pattern),
+            StackElValue::String(replacement),
+            StackElValue::String(input),
+        ) => {
+            let regex = Regex::new(pattern).map_err(|err| err.to_string())?;
+            let new_value = regex
+                .replace_all(input, replacement)
+                .map_err(|err| err.to_string())?;
+            let new_stack = [
+                vec![StackEl::new(StackElValue::String(new_value))],
+                stack[3..].to_vec(),
+            ]
+            .concat();
+
+            Ok(new_stack)
+        }
+        _ => Err(String::from("Only strings can be used to replace a pattern")),
+    }
+}
+
+pub fn type_of(stack: &Stack) -> Result<Stack, String> {
+    if stack.is_empty() {
+        return Err(String::from("Stack must be at least 1 element deep"));
+    }
+
+    let new_value = StackEl::new(StackElValue::String(stack[0].get_type().to_string()));
+    let new_stack = [vec![new_value], stack[1..].to_vec()].concat();
+
+    Ok(new_stack)
+}
+
+pub fn to_str(stack: &Stack) -> Result<Stack, String> {
+    if stack.is_empty() {
+        return Err(String::from("Stack must be at least 1 element deep"));
+    }
+
+    let new_value = StackEl::new(StackElValue::String(stack[0].value.to_string()));
+    let new_stack = [vec![new_value], stack[1..].to_vec()].concat();
+
+    Ok(new_stack)
+}
+
+pub fn parse_int(stack: &Stack) -> Result<Stack, String> {
+    if stack.is_empty() {
+        return Err(String::from("Stack must be at least 1 element deep"));
+    }
+
+    let new_value = match &stack[0].value {
+        StackElValue::String(val) => match val.parse::<i128>() {
+            Ok(int) => Ok(StackEl::new(StackElValue::Int(int))),
+            Err(_) => Err(format!("Cannot parse {:?} as int", val)),
+        },
+        _ => Err(format!(
+            "Cannot parse element of type {}",
+            stack[0].value.type_name()
+        )),
+    }?;
+
+    let new_stack = [vec![new_value], stack[1..].to_vec()].concat();
+
+    Ok(new_stack)
+}
+
+pub fn parse_bool(stack: &Stack) -> Result<Stack, String> {
+    if stack.is_empty() {
+        return Err(String::from("Stack must be at least 1 element deep"));
+    }
+
+    let new_value = match &stack[0].value {
+        StackElValue::String(val) => match val.parse() {
+            Ok(bool) => Ok(StackEl::new(StackElValue::Bool(bool))),
+            Err(_) => Err(format!("Cannot parse {:?} as bool", val)),
         },
         _ => Err(format!(
-            "Cannot index element of type {}",
-            stack[0].value.clone().to_string()
+            "Cannot parse element of type {}",
+            stack[0].value.type_name()
+        )),
+    }?;
+
+    let new_stack = [vec![new_value], stack[1..].to_vec()].concat();
+
+    Ok(new_stack)
+}
+
+pub fn explode(stack: &Stack) -> Result<Stack, String> {
+    if stack.is_empty() {
+        return Err(String::from("Stack must be at least 1 element deep"));
+    }
+
+    let new_value = match &stack[0].value {
+        StackElValue::String(val) => Ok(StackEl::new(StackElValue::Vector(
+            LifoVector::from_elements(Type::Char, val.chars().map(StackElValue::Char).collect()),
+        ))),
+        _ => Err(format!(
+            "Cannot explode element of type {}",
+            stack[0].value.type_name()
+        )),
+    }?;
+
+    let new_stack = [vec![new_value], stack[1..].to_vec()].concat();
+
+    Ok(new_stack)
+}
+
+pub fn implode(stack: &Stack) -> Result<Stack, String> {
+    if stack.is_empty() {
+        return Err(String::from("Stack must be at least 1 element deep"));
+    }
+
+    let new_value = match &stack[0].value {
+        StackElValue::Vector(val) => val
+            .elements
+            .iter()
+            .map(|el| match el {
+                StackElValue::Char(c) => Ok(*c),
+                _ => Err(format!(
+                    "Cannot implode vector of type {}",
+                    stack[0].value.type_name()
+                )),
+            })
+            .collect(),
+        _ => Err(format!(
+            "Cannot implode element of type {}",
+            stack[0].value.type_name()
         )),
     }?;
 
-    let new_stack = vec![vec![new_value], stack[1..].to_vec()].concat();
+    let new_stack = [
+        vec![StackEl::new(StackElValue::String(new_value))],
+        stack[1..].to_vec(),
+    ]
+    .concat();
 
     Ok(new_stack)
 }
+
+pub fn is_digit(stack: &Stack) -> Result<Stack, String> {
+    if stack.is_empty() {
+        return Err(String::from("Stack must be at least 1 element deep"));
+    }
+
+    match &stack[0].value {
+        StackElValue::Char(val) => {
+            let new_value = StackEl::new(StackElValue::Bool(val.is_ascii_digit()));
+            let new_stack = [vec![new_value], stack[1..].to_vec()].concat();
+
+            Ok(new_stack)
+        }
+        _ => Err(String::from("Only characters can be classified")),
+    }
+}
+
+pub fn is_alpha(stack: &Stack) -> Result<Stack, String> {
+    if stack.is_empty() {
+        return Err(String::from("Stack must be at least 1 element deep"));
+    }
+
+    match &stack[0].value {
+        StackElValue::Char(val) => {
+            let new_value = StackEl::new(StackElValue::Bool(val.is_alphabetic()));
+            let new_stack = [vec![new_value], stack[1..].to_vec()].concat();
+
+            Ok(new_stack)
+        }
+        _ => Err(String::from("Only characters can be classified")),
+    }
+}
+
+pub fn to_upper(stack: &Stack) -> Result<Stack, String> {
+    if stack.is_empty() {
+        return Err(String::from("Stack must be at least 1 element deep"));
+    }
+
+    let new_value = match &stack[0].value {
+        // characters like `ß` become several characters, they are kept as they are
+        StackElValue::Char(val) => {
+            let mut upper = val.to_uppercase();
+            let new_char = match (upper.next(), upper.next()) {
+                (Some(upper), None) => upper,
+                _ => *val,
+            };
+            Ok(StackEl::new(StackElValue::Char(new_char)))
+        }
+        StackElValue::String(val) => Ok(StackEl::new(StackElValue::String(val.to_uppercase()))),
+        _ => Err(String::from(
+            "Only characters and strings can be uppercased",
+        )),
+    }?;
+
+    let new_stack = [vec![new_value], stack[1..].to_vec()].concat();
+
+    Ok(new_stack)
+}
+
+// slices from `start` included to `end` excluded
+pub fn slice(stack: &Stack, start: usize, end: usize) -> Result<Stack, String> {
+    if stack.is_empty() {
+        return Err(String::from("Stack must be at least 1 element deep"));
+    }
+
+    let check_bounds = |len: usize| {
+        if start > end || end > len {
+            Err(format!(
+                "Out of bound slice {}..{} for {} of length {}",
+                start,
+                end,
+                stack[0].value.type_name(),
+                len
+            ))
+        } else {
+            Ok(())
+        }
+    };
+
+    let new_value = match &stack[0].value {
+        StackElValue::String(val) => {
+            let chars: Vec<char> = val.chars().collect();
+            check_bounds(chars.len())?;
+            Ok(StackEl::new(StackElValue::String(
+                chars[start..end].iter().collect(),
+            )))
+        }
+        StackElValue::Bytes(val) => {
+            check_bounds(val.len())?;
+            Ok(StackEl::new(StackElValue::Bytes(val[start..end].to_vec())))
+        }
+        StackElValue::Vector(val) => {
+            check_bounds(val.len())?;
+            Ok(StackEl::new(StackElValue::Vector(
+                LifoVector::from_elements(
+                    val.element_type.clone(),
+                    val.elements[start..end].to_vec(),
+                ),
+            )))
+        }
+        _ => Err(format!(
+            "Cannot slice element of type {}",
+            stack[0].value.type_name()
+        )),
+    }?;
+
+    let new_stack = [vec![new_value], stack[1..].to_vec()].concat();
+
+    Ok(new_stack)
+}
+
+// number of bytes of an int, the same on every platform
+const INT_WIDTH: usize = (i128::BITS / 8) as usize;
+
+// shorter byte strings are padded with zeros, only 16 bytes can give a negative int
+pub fn bytes_to_int(stack: &Stack, big_endian: bool) -> Result<Stack, String> {
+    if stack.is_empty() {
+        return Err(String::from("Stack must be at least 1 element deep"));
+    }
+
+    let new_value = match &stack[0].value {
+        StackElValue::Bytes(val) => {
+            let width = INT_WIDTH;
+            if val.len() > width {
+                return Err(format!(
+                    "Cannot convert {} bytes into an int, the maximum is {}",
+                    val.len(),
+                    width
+                ));
+            }
+            let mut padded = vec![0; width];
+            if big_endian {
+                padded[width - val.len()..].copy_from_slice(val);
+            } else {
+                padded[..val.len()].copy_from_slice(val);
+            }
+            let bytes = padded.try_into().unwrap();
+            let int = if big_endian {
+                i128::from_be_bytes(bytes)
+            } else {
+                i128::from_le_bytes(bytes)
+            };
+            Ok(StackEl::new(StackElValue::Int(int)))
+        }
+        _ => Err(format!(
+            "Cannot convert element of type {} into an int",
+            stack[0].value.type_name()
+        )),
+    }?;
+
+    let new_stack = [vec![new_value], stack[1..].to_vec()].concat();
+
+    Ok(new_stack)
+}
+
+// ints are always converted to `INT_WIDTH` bytes, negative ints in two's complement
+pub fn int_to_bytes(stack: &Stack, big_endian: bool) -> Result<Stack, String> {
+    if stack.is_empty() {
+        return Err(String::from("Stack must be at least 1 element deep"));
+    }
+
+    let new_value = match &stack[0].value {
+        StackElValue::Int(val) => {
+            let bytes = if big_endian {
+                val.to_be_bytes()
+            } else {
+                val.to_le_bytes()
+            };
+            Ok(StackEl::new(StackElValue::Bytes(bytes.to_vec())))
+        }
+        _ => Err(format!(
+            "Cannot convert element of type {} into bytes",
+            stack[0].value.type_name()
+        )),
+    }?;
+
+    let new_stack = [vec![new_value], stack[1..].to_vec()].concat();
+
+    Ok(new_stack)
+}
+
+pub fn bytes_to_str(stack: &Stack) -> Result<Stack, String> {
+    if stack.is_empty() {
+        return Err(String::from("Stack must be at least 1 element deep"));
+    }
+
+    let new_value = match &stack[0].value {
+        StackElValue::Bytes(val) => match String::from_utf8(val.clone()) {
+            Ok(str) => Ok(StackEl::new(StackElValue::String(str))),
+            Err(err) => Err(format!(
+                "Invalid UTF-8 sequence at byte {}",
+                err.utf8_error().valid_up_to()
+            )),
+        },
+        _ => Err(format!(
+            "Cannot decode element of type {}",
+            stack[0].value.type_name()
+        )),
+    }?;
+
+    let new_stack = [vec![new_value], stack[1..].to_vec()].concat();
+
+    Ok(new_stack)
+}
+
+pub fn str_to_bytes(stack: &Stack) -> Result<Stack, String> {
+    if stack.is_empty() {
+        return Err(String::from("Stack must be at least 1 element deep"));
+    }
+
+    let new_value = match &stack[0].value {
+        StackElValue::String(val) => Ok(StackEl::new(StackElValue::Bytes(val.as_bytes().to_vec()))),
+        _ => Err(format!(
+            "Cannot encode element of type {}",
+            stack[0].value.type_name()
+        )),
+    }?;
+
+    let new_stack = [vec![new_value], stack[1..].to_vec()].concat();
+
+    Ok(new_stack)
+}
+
+pub fn set(stack: &Stack, index: usize) -> Result<Stack, String> {
+    if stack.len() < 2 {
+        return Err(String::from("Stack must be at least 2 elements deep"));
+    }
+
+    match (&stack[0].value, &stack[1].value) {
+        (new_value, StackElValue::Vector(lifo_vector)) => {
+            let new_vector = lifo_vector.clone().set(index, new_value.clone())?;
+            let new_stack = [
+                vec![StackEl::new(StackElValue::Vector(new_vector))],
+                stack[2..].to_vec(),
+            ]
+            .concat();
+
+            Ok(new_stack)
+        }
+        _ => Err(String::from("Invalid stack to set an element in a vector")),
+    }
+}
+
+pub fn insert_at(stack: &Stack, index: usize) -> Result<Stack, String> {
+    if stack.len() < 2 {
+        return Err(String::from("Stack must be at least 2 elements deep"));
+    }
+
+    match (&stack[0].value, &stack[1].value) {
+        (val_to_insert, StackElValue::Vector(lifo_vector)) => {
+            let new_vector = lifo_vector.clone().insert_at(index, val_to_insert.clone())?;
+            let new_stack = [
+                vec![StackEl::new(StackElValue::Vector(new_vector))],
+                stack[2..].to_vec(),
+            ]
+            .concat();
+
+            Ok(new_stack)
+        }
+        _ => Err(String::from(
+            "Invalid stack to insert an element in a vector",
+        )),
+    }
+}
+
+// pushes the vector without the element, then the removed element on top of it
+pub fn remove(stack: &Stack, index: usize) -> Result<Stack, String> {
+    if stack.is_empty() {
+        return Err(String::from("Stack must be at least 1 element deep"));
+    }
+
+    match &stack[0].value {
+        StackElValue::Vector(lifo_vector) => {
+            let (new_vector, removed) = lifo_vector.clone().remove(index)?;
+            let new_stack = [
+                vec![
+                    StackEl::new(removed),
+                    StackEl::new(StackElValue::Vector(new_vector)),
+                ],
+                stack[1..].to_vec(),
+            ]
+            .concat();
+
+            Ok(new_stack)
+        }
+        _ => Err(format!(
+            "Cannot remove an element from element of type {}",
+            stack[0].value.type_name()
+        )),
+    }
+}
+
+pub fn pop_back(stack: &Stack) -> Result<Stack, String> {
+    if stack.is_empty() {
+        return Err(String::from("Stack must be at least 1 element deep"));
+    }
+
+    match &stack[0].value {
+        StackElValue::Vector(lifo_vector) => {
+            if lifo_vector.is_empty() {
+                return Err(String::from("Cannot pop an element from an empty vector"));
+            }
+            remove(stack, lifo_vector.len() - 1)
+        }
+        _ => Err(format!(
+            "Cannot pop an element from element of type {}",
+            stack[0].value.type_name()
+        )),
+    }
+}
+
+// compares the elements one by one, a prefix comes before the longer sequence
+fn lexicographic_order<'a>(
+    a: impl IntoIterator<Item = &'a StackElValue>,
+    b: impl IntoIterator<Item = &'a StackElValue>,
+) -> Option<Ordering> {
+    let mut b = b.into_iter();
+    for a_el in a {
+        match b.next() {
+            None => return Some(Ordering::Greater),
+            Some(b_el) => match natural_order(a_el, b_el)? {
+                Ordering::Equal => (),
+                ordering => return Some(ordering),
+            },
+        }
+    }
+
+    Some(if b.next().is_none() {
+        Ordering::Equal
+    } else {
+        Ordering::Less
+    })
+}
+
+// finds a value among items sorted by their natural order, like `binary_search`
+fn sorted_search<T>(
+    items: &[T],
+    value: &StackElValue,
+    key: impl Fn(&T) -> &StackElValue,
+) -> Result<usize, usize> {
+    items.binary_search_by(|item| natural_order(key(item), value).unwrap_or(Ordering::Less))
+}
+
+// total ordering of values of the same type, false comes before true.
+// values of different types can't be ordered
+fn natural_order(a: &StackElValue, b: &StackElValue) -> Option<Ordering> {
+    match (a, b) {
+        (StackElValue::Int(a), StackElValue::Int(b)) => Some(a.cmp(b)),
+        (StackElValue::Bool(a), StackElValue::Bool(b)) => Some(a.cmp(b)),
+        (StackElValue::String(a), StackElValue::String(b)) => Some(a.cmp(b)),
+        (StackElValue::Char(a), StackElValue::Char(b)) => Some(a.cmp(b)),
+        (StackElValue::Bytes(a), StackElValue::Bytes(b)) => Some(a.cmp(b)),
+        // pairs are ordered by their first element, then by their second element
+        (StackElValue::Pair(a_first, a_second), StackElValue::Pair(b_first, b_second)) => {
+            match natural_order(a_first, b_first)? {
+                Ordering::Equal => natural_order(a_second, b_second),
+                ordering => Some(ordering),
+            }
+        }
+        // none comes before any value
+        (StackElValue::Option(_, a), StackElValue::Option(_, b)) => match (a, b) {
+            (Some(a), Some(b)) => natural_order(a, b),
+            _ => Some(a.is_some().cmp(&b.is_some())),
+        },
+        // left values come before right values
+        (StackElValue::Left(a, _), StackElValue::Left(b, _)) => natural_order(a, b),
+        (StackElValue::Right(_, a), StackElValue::Right(_, b)) => natural_order(a, b),
+        (StackElValue::Left(_, _), StackElValue::Right(_, _)) => Some(Ordering::Less),
+        (StackElValue::Right(_, _), StackElValue::Left(_, _)) => Some(Ordering::Greater),
+        // collections are compared lexicographically, maps entry by entry with the key first
+        (StackElValue::Vector(a), StackElValue::Vector(b)) => {
+            lexicographic_order(&a.elements, &b.elements)
+        }
+        (StackElValue::Set(a), StackElValue::Set(b)) => lexicographic_order(&a.elements, &b.elements),
+        (StackElValue::Map(a), StackElValue::Map(b)) => lexicographic_order(
+            a.entries.iter().flat_map(|(key, value)| [key, value]),
+            b.entries.iter().flat_map(|(key, value)| [key, value]),
+        ),
+        // records with the same fields are compared field by field, in the order of the field names
+        (StackElValue::Record(a), StackElValue::Record(b)) => {
+            let same_fields = a.fields.len() == b.fields.len()
+                && a.fields.iter().zip(&b.fields).all(|((a, _), (b, _))| a == b);
+            if !same_fields {
+                return None;
+            }
+            lexicographic_order(
+                a.fields.iter().map(|(_, value)| value),
+                b.fields.iter().map(|(_, value)| value),
+            )
+        }
+        _ => None,
+    }
+}
+
+pub fn sort(stack: &Stack) -> Result<Stack, String> {
+    if stack.is_empty() {
+        return Err(String::from("Stack must be at least 1 element deep"));
+    }
+
+    match &stack[0].value {
+        StackElValue::Vector(val) => {
+            let mut elements = val.elements.clone();
+            let mut sortable = true;
+            // the sort is stable, equal elements keep their order
+            elements.sort_by(|a, b| {
+                natural_order(a, b).unwrap_or_else(|| {
+                    sortable = false;
+                    Ordering::Equal
+                })
+            });
+            if !sortable {
+                return Err(format!(
+                    "Cannot sort vector of type {}",
+                    stack[0].value.type_name()
+                ));
+            }
+
+            let new_vector = LifoVector::from_elements(val.element_type.clone(), elements);
+            let new_stack = [
+                vec![StackEl::new(StackElValue::Vector(new_vector))],
+                stack[1..].to_vec(),
+            ]
+            .concat();
+
+            Ok(new_stack)
+        }
+        _ => Err(format!(
+            "Cannot sort element of type {}",
+            stack[0].value.type_name()
+        )),
+    }
+}
+
+pub fn reverse(stack: &Stack) -> Result<Stack, String> {
+    if stack.is_empty() {
+        return Err(String::from("Stack must be at least 1 element deep"));
+    }
+
+    match &stack[0].value {
+        StackElValue::Vector(val) => {
+            let elements = val.elements.iter().rev().cloned().collect();
+            let new_vector = LifoVector::from_elements(val.element_type.clone(), elements);
+            let new_stack = [
+                vec![StackEl::new(StackElValue::Vector(new_vector))],
+                stack[1..].to_vec(),
+            ]
+            .concat();
+
+            Ok(new_stack)
+        }
+        _ => Err(format!(
+            "Cannot reverse element of type {}",
+            stack[0].value.type_name()
+        )),
+    }
+}
+
+// finds the position of the element on top of the stack in the vector below it
+fn position(stack: &Stack) -> Result<(Option<usize>, usize), String> {
+    if stack.len() < 2 {
+        return Err(String::from("Stack must be at least 2 elements deep"));
+    }
+
+    match (&stack[0].value, &stack[1].value) {
+        (el, StackElValue::Vector(val)) => {
+            if val.element_type != Type::Any && val.element_type.unify(&el.get_type()).is_none() {
+                return Err(format!(
+                    "Cannot search value of type {} in vector of type {}",
+                    el.type_name(),
+                    stack[1].value.type_name()
+                ));
+            }
+
+            Ok((val.elements.iter().position(|x| same_value(x, el)), val.len()))
+        }
+        _ => Err(String::from("Invalid stack to search an element in a vector")),
+    }
+}
+
+pub fn contains(stack: &Stack) -> Result<Stack, String> {
+    let found = match stack.get(1).map(|el| &el.value) {
+        Some(StackElValue::Set(set)) => set.contains(&stack[0].value)?,
+        _ => position(stack)?.0.is_some(),
+    };
+    let new_value = StackEl::new(StackElValue::Bool(found));
+    let new_stack = [vec![new_value], stack[2..].to_vec()].concat();
+
+    Ok(new_stack)
+}
+
+// pushes the index of the first occurrence of the element,
+// or the length of the vector when the element is missing (like an index right after the last element)
+pub fn index_of(stack: &Stack) -> Result<Stack, String> {
+    let (position, len) = position(stack)?;
+    let new_value = StackEl::new(StackElValue::Int(position.unwrap_or(len) as i128));
+    let new_stack = [vec![new_value], stack[2..].to_vec()].concat();
+
+    Ok(new_stack)
+}
+
+pub fn put(stack: &Stack) -> Result<Stack, String> {
+    if stack.len() < 3 {
+        return Err(String::from("Stack must be at least 3 elements deep"));
+    }
+
+    match (&stack[0].value, &stack[1].value, &stack[2].value) {
+        (value, key, StackElValue::Map(map)) => {
+            let new_map = map.clone().put(key.clone(), value.clone())?;
+            let new_stack = [
+                vec![StackEl::new(StackElValue::Map(new_map))],
+                stack[3..].to_vec(),
+            ]
+            .concat();
+
+            Ok(new_stack)
+        }
+        _ => Err(String::from("Invalid stack to put an entry in a map")),
+    }
+}
+
+pub fn get(stack: &Stack) -> Result<Stack, String> {
+    if stack.len() < 2 {
+        return Err(String::from("Stack must be at least 2 elements deep"));
+    }
+
+    match (&stack[0].value, &stack[1].value) {
+        (key, StackElValue::Map(map)) => match map.get(key)? {
+            None => Err(format!("Key {} not found in map", render_element(key))),
+            Some(value) => {
+                let new_stack = [vec![StackEl::new(value.clone())], stack[2..].to_vec()].concat();
+
+                Ok(new_stack)
+            }
+        },
+        _ => Err(String::from("Invalid stack to get an entry from a map")),
+    }
+}
+
+pub fn del(stack: &Stack) -> Result<Stack, String> {
+    if stack.len() < 2 {
+        return Err(String::from("Stack must be at least 2 elements deep"));
+    }
+
+    match (&stack[0].value, &stack[1].value) {
+        (key, StackElValue::Map(map)) => {
+            let new_map = map.clone().remove(key)?;
+            let new_stack = [
+                vec![StackEl::new(StackElValue::Map(new_map))],
+                stack[2..].to_vec(),
+            ]
+            .concat();
+
+            Ok(new_stack)
+        }
+        (el, StackElValue::Set(set)) => {
+            let new_set = set.clone().remove(el)?;
+            let new_stack = [
+                vec![StackEl::new(StackElValue::Set(new_set))],
+                stack[2..].to_vec(),
+            ]
+            .concat();
+
+            Ok(new_stack)
+        }
+        _ => Err(String::from("Invalid stack to delete an entry from a map")),
+    }
+}
+
+pub fn has(stack: &Stack) -> Result<Stack, String> {
+    if stack.len() < 2 {
+        return Err(String::from("Stack must be at least 2 elements deep"));
+    }
+
+    match (&stack[0].value, &stack[1].value) {
+        (key, StackElValue::Map(map)) => {
+            let found = map.get(key)?.is_some();
+            let new_stack = [
+                vec![StackEl::new(StackElValue::Bool(found))],
+                stack[2..].to_vec(),
+            ]
+            .concat();
+
+            Ok(new_stack)
+        }
+        _ => Err(String::from("Invalid stack to search a key in a map")),
+    }
+}
+
+pub fn keys(stack: &Stack) -> Result<Stack, String> {
+    if stack.is_empty() {
+        return Err(String::from("Stack must be at least 1 element deep"));
+    }
+
+    match &stack[0].value {
+        StackElValue::Map(map) => {
+            let keys = map.entries.iter().map(|(key, _)| key.clone()).collect();
+            let new_vector = LifoVector::from_elements(map.key_type.clone(), keys);
+            let new_stack = [
+                vec![StackEl::new(StackElValue::Vector(new_vector))],
+                stack[1..].to_vec(),
+            ]
+            .concat();
+
+            Ok(new_stack)
+        }
+        _ => Err(format!(
+            "Cannot list the keys of element of type {}",
+            stack[0].value.type_name()
+        )),
+    }
+}
+
+// the values come in the order of their keys
+pub fn values(stack: &Stack) -> Result<Stack, String> {
+    if stack.is_empty() {
+        return Err(String::from("Stack must be at least 1 element deep"));
+    }
+
+    match &stack[0].value {
+        StackElValue::Map(map) => {
+            let values = map.entries.iter().map(|(_, value)| value.clone()).collect();
+            let new_vector = LifoVector::from_elements(map.value_type.clone(), values);
+            let new_stack = [
+                vec![StackEl::new(StackElValue::Vector(new_vector))],
+                stack[1..].to_vec(),
+            ]
+            .concat();
+
+            Ok(new_stack)
+        }
+        _ => Err(format!(
+            "Cannot list the values of element of type {}",
+            stack[0].value.type_name()
+        )),
+    }
+}
+
+// combines the set on top of the stack with the set below it
+fn combine_sets(
+    stack: &Stack,
+    combine: fn(LifoSet, &LifoSet) -> Result<LifoSet, String>,
+) -> Result<Stack, String> {
+    if stack.len() < 2 {
+        return Err(String::from("Stack must be at least 2 elements deep"));
+    }
+
+    match (&stack[0].value, &stack[1].value) {
+        (StackElValue::Set(set1), StackElValue::Set(set2)) => {
+            let new_set = combine(set1.clone(), set2)?;
+            let new_stack = [
+                vec![StackEl::new(StackElValue::Set(new_set))],
+                stack[2..].to_vec(),
+            ]
+            .concat();
+
+            Ok(new_stack)
+        }
+        _ => Err(String::from("Only sets can be combined")),
+    }
+}
+
+pub fn union(stack: &Stack) -> Result<Stack, String> {
+    combine_sets(stack, LifoSet::union)
+}
+
+pub fn intersect(stack: &Stack) -> Result<Stack, String> {
+    combine_sets(stack, LifoSet::intersect)
+}
+
+// keeps the elements of the set on top of the stack that are missing from the set below it
+pub fn diff(stack: &Stack) -> Result<Stack, String> {
+    combine_sets(stack, LifoSet::difference)
+}
+
+// duplicates are removed and the elements are sorted
+pub fn to_set(stack: &Stack) -> Result<Stack, String> {
+    if stack.is_empty() {
+        return Err(String::from("Stack must be at least 1 element deep"));
+    }
+
+    match &stack[0].value {
+        StackElValue::Vector(val) => {
+            let set = LifoSet::of(val.element_type.clone());
+            let new_set = val
+                .elements
+                .iter()
+                .try_fold(set, |set, el| set.insert(el.clone()))?;
+            let new_stack = [
+                vec![StackEl::new(StackElValue::Set(new_set))],
+                stack[1..].to_vec(),
+            ]
+            .concat();
+
+            Ok(new_stack)
+        }
+        _ => Err(format!(
+            "Cannot convert element of type {} into a set",
+            stack[0].value.type_name()
+        )),
+    }
+}
+
+pub fn to_vector(stack: &Stack) -> Result<Stack, String> {
+    if stack.is_empty() {
+        return Err(String::from("Stack must be at least 1 element deep"));
+    }
+
+    match &stack[0].value {
+        StackElValue::Set(set) => {
+            let new_vector =
+                LifoVector::from_elements(set.element_type.clone(), set.elements.clone());
+            let new_stack = [
+                vec![StackEl::new(StackElValue::Vector(new_vector))],
+                stack[1..].to_vec(),
+            ]
+            .concat();
+
+            Ok(new_stack)
+        }
+        _ => Err(format!(
+            "Cannot convert element of type {} into a vector",
+            stack[0].value.type_name()
+        )),
+    }
+}
+
+// the element on top of the stack becomes the first element of the pair
+pub fn pair(stack: &Stack) -> Result<Stack, String> {
+    if stack.len() < 2 {
+        return Err(String::from("Stack must be at least 2 elements deep"));
+    }
+
+    let new_value = StackElValue::Pair(
+        Box::new(stack[0].value.clone()),
+        Box::new(stack[1].value.clone()),
+    );
+    let new_stack = [vec![StackEl::new(new_value)], stack[2..].to_vec()].concat();
+
+    Ok(new_stack)
+}
+
+// leaves the first element of the pair on top of the second one
+pub fn unpair(stack: &Stack) -> Result<Stack, String> {
+    if stack.is_empty() {
+        return Err(String::from("Stack must be at least 1 element deep"));
+    }
+
+    match &stack[0].value {
+        StackElValue::Pair(first, second) => {
+            let new_stack = [
+                vec![StackEl::new(*first.clone()), StackEl::new(*second.clone())],
+                stack[1..].to_vec(),
+            ]
+            .concat();
+
+            Ok(new_stack)
+        }
+        _ => Err(format!(
+            "Cannot unpair element of type {}",
+            stack[0].value.type_name()
+        )),
+    }
+}
+
+pub fn car(stack: &Stack) -> Result<Stack, String> {
+    if stack.is_empty() {
+        return Err(String::from("Stack must be at least 1 element deep"));
+    }
+
+    match &stack[0].value {
+        StackElValue::Pair(first, _) => {
+            let new_stack = [vec![StackEl::new(*first.clone())], stack[1..].to_vec()].concat();
+
+            Ok(new_stack)
+        }
+        _ => Err(format!(
+            "Cannot get the first element of element of type {}",
+            stack[0].value.type_name()
+        )),
+    }
+}
+
+pub fn cdr(stack: &Stack) -> Result<Stack, String> {
+    if stack.is_empty() {
+        return Err(String::from("Stack must be at least 1 element deep"));
+    }
+
+    match &stack[0].value {
+        StackElValue::Pair(_, second) => {
+            let new_stack = [vec![StackEl::new(*second.clone())], stack[1..].to_vec()].concat();
+
+            Ok(new_stack)
+        }
+        _ => Err(format!(
+            "Cannot get the second element of element of type {}",
+            stack[0].value.type_name()
+        )),
+    }
+}
+
+pub fn get_field(stack: &Stack, name: &str) -> Result<Stack, String> {
+    if stack.is_empty() {
+        return Err(String::from("Stack must be at least 1 element deep"));
+    }
+
+    match &stack[0].value {
+        StackElValue::Record(record) => {
+            let new_stack = [
+                vec![StackEl::new(record.get(name)?.clone())],
+                stack[1..].to_vec(),
+            ]
+            .concat();
+
+            Ok(new_stack)
+        }
+        _ => Err(format!(
+            "Cannot get field `{}` of element of type {}",
+            name,
+            stack[0].value.type_name()
+        )),
+    }
+}
+
+// the new value is on top of the stack, the record below it
+pub fn set_field(stack: &Stack, name: &str) -> Result<Stack, String> {
+    if stack.len() < 2 {
+        return Err(String::from("Stack must be at least 2 elements deep"));
+    }
+
+    match (&stack[0].value, &stack[1].value) {
+        (new_value, StackElValue::Record(record)) => {
+            let new_record = record.clone().set(name, new_value.clone())?;
+            let new_stack = [
+                vec![StackEl::new(StackElValue::Record(new_record))],
+                stack[2..].to_vec(),
+            ]
+            .concat();
+
+            Ok(new_stack)
+        }
+        _ => Err(String::from("Invalid stack to set a field in a record")),
+    }
+}
+
+pub fn some(stack: &Stack) -> Result<Stack, String> {
+    if stack.is_empty() {
+        return Err(String::from("Stack must be at least 1 element deep"));
+    }
+
+    let new_value = StackElValue::Option(
+        stack[0].value.get_type(),
+        Some(Box::new(stack[0].value.clone())),
+    );
+    let new_stack = [vec![StackEl::new(new_value)], stack[1..].to_vec()].concat();
+
+    Ok(new_stack)
+}
+
+pub fn unwrap(stack: &Stack) -> Result<Stack, String> {
+    if stack.is_empty() {
+        return Err(String::from("Stack must be at least 1 element deep"));
+    }
+
+    match &stack[0].value {
+        StackElValue::Option(_, Some(val)) => {
+            let new_stack = [vec![StackEl::new(*val.clone())], stack[1..].to_vec()].concat();
+
+            Ok(new_stack)
+        }
+        StackElValue::Option(_, None) => Err(format!(
+            "Cannot unwrap none value of type {}",
+            stack[0].value.type_name()
+        )),
+        _ => Err(format!(
+            "Cannot unwrap element of type {}",
+            stack[0].value.type_name()
+        )),
+    }
+}
+
+// unwraps the option on top of the stack, also tells if it was none
+pub fn if_none(stack: &Stack) -> Result<(Stack, bool), String> {
+    if stack.is_empty() {
+        return Err(String::from("Stack must be at least 1 element deep"));
+    }
+
+    match &stack[0].value {
+        StackElValue::Option(_, Some(val)) => {
+            let new_stack = [vec![StackEl::new(*val.clone())], stack[1..].to_vec()].concat();
+
+            Ok((new_stack, false))
+        }
+        StackElValue::Option(_, None) => Ok((stack[1..].to_vec(), true)),
+        _ => Err(format!(
+            "Top element must be an option, found {}",
+            stack[0].value.type_name()
+        )),
+    }
+}
+
+pub fn left(stack: &Stack, right_type: Type) -> Result<Stack, String> {
+    if stack.is_empty() {
+        return Err(String::from("Stack must be at least 1 element deep"));
+    }
+
+    let new_value = StackElValue::Left(Box::new(stack[0].value.clone()), right_type);
+    let new_stack = [vec![StackEl::new(new_value)], stack[1..].to_vec()].concat();
+
+    Ok(new_stack)
+}
+
+pub fn right(stack: &Stack, left_type: Type) -> Result<Stack, String> {
+    if stack.is_empty() {
+        return Err(String::from("Stack must be at least 1 element deep"));
+    }
+
+    let new_value = StackElValue::Right(left_type, Box::new(stack[0].value.clone()));
+    let new_stack = [vec![StackEl::new(new_value)], stack[1..].to_vec()].concat();
+
+    Ok(new_stack)
+}
+
+// unwraps the or value on top of the stack, also tells if it was a left value
+pub fn if_left(stack: &Stack) -> Result<(Stack, bool), String> {
+    if stack.is_empty() {
+        return Err(String::from("Stack must be at least 1 element deep"));
+    }
+
+    let (val, is_left) = match &stack[0].value {
+        StackElValue::Left(val, _) => (val, true),
+        StackElValue::Right(_, val) => (val, false),
+        _ => {
+            return Err(format!(
+                "Cannot unwrap value of type {}, only or values can be unwrapped",
+                stack[0].value.type_name()
+            ))
+        }
+    };
+    let new_stack = [vec![StackEl::new(*val.clone())], stack[1..].to_vec()].concat();
+
+    Ok((new_stack, is_left))
+}
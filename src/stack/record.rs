@@ -0,0 +1,79 @@
+use super::{render_element, StackElValue, Type};
+use std::fmt;
+
+// fields are kept sorted by name so that records with the same fields are equal whatever their order
+#[derive(Debug, Clone, PartialEq)]
+pub struct LifoRecord {
+    pub fields: Vec<(String, StackElValue)>,
+}
+impl LifoRecord {
+    pub fn from_fields(fields: Vec<(String, StackElValue)>) -> Result<Self, String> {
+        let mut sorted: Vec<(String, StackElValue)> = vec![];
+        for (name, value) in fields {
+            match sorted.binary_search_by(|(field, _)| field.cmp(&name)) {
+                Ok(_) => return Err(format!("Duplicate field `{}` in record", name)),
+                Err(i) => sorted.insert(i, (name, value)),
+            }
+        }
+
+        Ok(LifoRecord { fields: sorted })
+    }
+
+    pub fn record_type(&self) -> Type {
+        Type::Record(
+            self.fields
+                .iter()
+                .map(|(name, value)| (name.clone(), value.get_type()))
+                .collect(),
+        )
+    }
+
+    fn search(&self, name: &str) -> Result<usize, String> {
+        self.fields
+            .binary_search_by(|(field, _)| field.as_str().cmp(name))
+            .map_err(|_| {
+                format!(
+                    "Record of type {} has no field `{}`",
+                    self.record_type(),
+                    name
+                )
+            })
+    }
+
+    pub fn get(&self, name: &str) -> Result<&StackElValue, String> {
+        let i = self.search(name)?;
+
+        Ok(&self.fields[i].1)
+    }
+
+    // records have a fixed shape, the new value must have the type of the field
+    pub fn set(self, name: &str, value: StackElValue) -> Result<Self, String> {
+        let i = self.search(name)?;
+        let field_type = self.fields[i].1.get_type();
+        if field_type.unify(&value.get_type()).is_none() {
+            return Err(format!(
+                "Cannot set field `{}` of type {} to value of type {}",
+                name,
+                field_type,
+                value.type_name()
+            ));
+        }
+
+        let mut fields = self.fields;
+        fields[i].1 = value;
+
+        Ok(LifoRecord { fields })
+    }
+}
+
+impl fmt::Display for LifoRecord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let fields: Vec<String> = self
+            .fields
+            .iter()
+            .map(|(name, value)| format!("{}: {}", name, render_element(value)))
+            .collect();
+
+        write!(f, "#{{{}}}", fields.join(", "))
+    }
+}
@@ -0,0 +1,140 @@
+use super::{natural_order, render_element, sorted_search, StackElValue, Type};
+use std::fmt;
+
+// elements are kept sorted so that sets are always iterated in the same order
+#[derive(Debug, Clone, PartialEq)]
+pub struct LifoSet {
+    pub element_type: Type,
+    pub elements: Vec<StackElValue>,
+}
+impl LifoSet {
+    pub fn new() -> Self {
+        LifoSet::of(Type::Unknown)
+    }
+
+    pub fn of(element_type: Type) -> Self {
+        LifoSet {
+            element_type,
+            elements: vec![],
+        }
+    }
+
+    fn set_type(&self) -> Type {
+        Type::Set(Box::new(self.element_type.clone()))
+    }
+
+    // sets of unknown type take the type of the first element inserted
+    fn check_element(&self, el: &StackElValue) -> Result<Type, String> {
+        // only values with a natural ordering can be sorted, and values nested in an `any`
+        // collection may not be comparable with the ones of the other elements
+        if natural_order(el, el).is_none() || el.get_type().contains_any() {
+            return Err(format!(
+                "Cannot use value of type {} as a set element",
+                el.type_name()
+            ));
+        }
+
+        self.element_type.unify(&el.get_type()).ok_or(format!(
+            "Cannot insert value of type {} into set of type {}",
+            el.type_name(),
+            self.set_type()
+        ))
+    }
+
+    fn check_set(&self, other: &LifoSet) -> Result<Type, String> {
+        self.element_type.unify(&other.element_type).ok_or(format!(
+            "Cannot combine set of type {} with set of type {}",
+            self.set_type(),
+            other.set_type()
+        ))
+    }
+
+    fn search(&self, el: &StackElValue) -> Result<usize, usize> {
+        sorted_search(&self.elements, el, |x| x)
+    }
+
+    pub fn insert(self, el: StackElValue) -> Result<Self, String> {
+        let element_type = self.check_element(&el)?;
+        let position = self.search(&el);
+        let mut elements = self.elements;
+        if let Err(i) = position {
+            elements.insert(i, el);
+        }
+
+        Ok(LifoSet {
+            element_type,
+            elements,
+        })
+    }
+
+    // removing a missing element leaves the set unchanged
+    pub fn remove(self, el: &StackElValue) -> Result<Self, String> {
+        self.check_element(el)?;
+        let position = self.search(el);
+        let mut elements = self.elements;
+        if let Ok(i) = position {
+            elements.remove(i);
+        }
+
+        Ok(LifoSet { elements, ..self })
+    }
+
+    pub fn contains(&self, el: &StackElValue) -> Result<bool, String> {
+        self.check_element(el)?;
+
+        Ok(self.search(el).is_ok())
+    }
+
+    pub fn union(self, other: &LifoSet) -> Result<Self, String> {
+        let element_type = self.check_set(other)?;
+        let set = LifoSet {
+            element_type,
+            ..self
+        };
+
+        other
+            .elements
+            .iter()
+            .try_fold(set, |set, el| set.insert(el.clone()))
+    }
+
+    pub fn intersect(self, other: &LifoSet) -> Result<Self, String> {
+        let element_type = self.check_set(other)?;
+        let elements = self
+            .elements
+            .into_iter()
+            .filter(|el| other.search(el).is_ok())
+            .collect();
+
+        Ok(LifoSet {
+            element_type,
+            elements,
+        })
+    }
+
+    pub fn difference(self, other: &LifoSet) -> Result<Self, String> {
+        let element_type = self.check_set(other)?;
+        let elements = self
+            .elements
+            .into_iter()
+            .filter(|el| other.search(el).is_err())
+            .collect();
+
+        Ok(LifoSet {
+            element_type,
+            elements,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.elements.len()
+    }
+}
+
+impl fmt::Display for LifoSet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let elements: Vec<String> = self.elements.iter().map(render_element).collect();
+
+        write!(f, "{{{}}}", elements.join(", "))
+    }
+}
@@ -0,0 +1,219 @@
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Type {
+    Int,
+    Bool,
+    String,
+    Char,
+    Bytes,
+    Vector(Box<Type>),
+    Map(Box<Type>, Box<Type>),
+    Set(Box<Type>),
+    Pair(Box<Type>, Box<Type>),
+    Option(Box<Type>),
+    Or(Box<Type>, Box<Type>),
+    // fields sorted by name
+    Record(Vec<(String, Type)>),
+    Quotation,
+    // element type of the vectors that accept values of any type
+    Any,
+    // element type of the vectors that haven't received any value yet
+    Unknown,
+}
+impl Type {
+    // finds the most precise type compatible with both types, unknown types are compatible with anything
+    pub fn unify(&self, other: &Type) -> Option<Type> {
+        match (self, other) {
+            (Type::Unknown, other) => Some(other.clone()),
+            (this, Type::Unknown) => Some(this.clone()),
+            (Type::Vector(this), Type::Vector(other)) => {
+                this.unify(other).map(|el_type| Type::Vector(Box::new(el_type)))
+            }
+            (Type::Option(this), Type::Option(other)) => {
+                this.unify(other).map(|el_type| Type::Option(Box::new(el_type)))
+            }
+            (Type::Set(this), Type::Set(other)) => {
+                this.unify(other).map(|el_type| Type::Set(Box::new(el_type)))
+            }
+            (Type::Pair(this_first, this_second), Type::Pair(other_first, other_second)) => {
+                Some(Type::Pair(
+                    Box::new(this_first.unify(other_first)?),
+                    Box::new(this_second.unify(other_second)?),
+                ))
+            }
+            (Type::Record(this), Type::Record(other)) if this.len() == other.len() => this
+                .iter()
+                .zip(other)
+                .map(|((this_name, this_type), (other_name, other_type))| {
+                    if this_name != other_name {
+                        return None;
+                    }
+                    Some((this_name.clone(), this_type.unify(other_type)?))
+                })
+                .collect::<Option<Vec<_>>>()
+                .map(Type::Record),
+            (Type::Or(this_left, this_right), Type::Or(other_left, other_right)) => Some(Type::Or(
+                Box::new(this_left.unify(other_left)?),
+                Box::new(this_right.unify(other_right)?),
+            )),
+            (Type::Map(this_key, this_value), Type::Map(other_key, other_value)) => Some(Type::Map(
+                Box::new(this_key.unify(other_key)?),
+                Box::new(this_value.unify(other_value)?),
+            )),
+            (this, other) if this == other => Some(this.clone()),
+            _ => None,
+        }
+    }
+
+    // values of a type containing any can't all be compared with each other
+    pub fn contains_any(&self) -> bool {
+        match self {
+            Type::Any => true,
+            Type::Vector(el_type) | Type::Set(el_type) | Type::Option(el_type) => el_type.contains_any(),
+            Type::Map(first, second) | Type::Pair(first, second) | Type::Or(first, second) => {
+                first.contains_any() || second.contains_any()
+            }
+            Type::Record(fields) => fields.iter().any(|(_, field_type)| field_type.contains_any()),
+            _ => false,
+        }
+    }
+
+    // reads a type name at the start of the source, returns the type and the length of its name
+    pub fn parse(src: &str) -> Option<(Type, usize)> {
+        if let Some(rest) = src.strip_prefix("vector<") {
+            let (el_type, len) = Type::parse(rest)?;
+            if !rest[len..].starts_with('>') {
+                return None;
+            }
+            return Some((Type::Vector(Box::new(el_type)), "vector<".len() + len + 1));
+        }
+        if let Some(rest) = src.strip_prefix("set<") {
+            let (el_type, len) = Type::parse(rest)?;
+            if !rest[len..].starts_with('>') {
+                return None;
+            }
+            return Some((Type::Set(Box::new(el_type)), "set<".len() + len + 1));
+        }
+        if let Some(rest) = src.strip_prefix("option<") {
+            let (el_type, len) = Type::parse(rest)?;
+            if !rest[len..].starts_with('>') {
+                return None;
+            }
+            return Some((Type::Option(Box::new(el_type)), "option<".len() + len + 1));
+        }
+        if let Some(rest) = src.strip_prefix("map<") {
+            let (key_type, value_type, len) = Type::parse_two(rest)?;
+            return Some((
+                Type::Map(Box::new(key_type), Box::new(value_type)),
+                "map<".len() + len,
+            ));
+        }
+        if let Some(rest) = src.strip_prefix("pair<") {
+            let (first_type, second_type, len) = Type::parse_two(rest)?;
+            return Some((
+                Type::Pair(Box::new(first_type), Box::new(second_type)),
+                "pair<".len() + len,
+            ));
+        }
+        if let Some(rest) = src.strip_prefix("or<") {
+            let (left_type, right_type, len) = Type::parse_two(rest)?;
+            return Some((
+                Type::Or(Box::new(left_type), Box::new(right_type)),
+                "or<".len() + len,
+            ));
+        }
+        if let Some(rest) = src.strip_prefix("record<") {
+            let (fields, len) = Type::parse_fields(rest)?;
+            return Some((Type::Record(fields), "record<".len() + len));
+        }
+
+        let len = src
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .unwrap_or(src.len());
+        let parsed = match &src[..len] {
+            "int" => Type::Int,
+            "bool" => Type::Bool,
+            "string" => Type::String,
+            "char" => Type::Char,
+            "bytes" => Type::Bytes,
+            "quotation" => Type::Quotation,
+            "any" => Type::Any,
+            _ => return None,
+        };
+
+        Some((parsed, len))
+    }
+
+    // reads the `name: type` pairs of a record, including the closing bracket
+    fn parse_fields(src: &str) -> Option<(Vec<(String, Type)>, usize)> {
+        // positions are computed from the length of what remains to be read
+        let offset = |rest: &str| src.len() - rest.trim_start().len();
+        let mut fields: Vec<(String, Type)> = vec![];
+        let mut pos = offset(src);
+        while !src[pos..].starts_with('>') {
+            if !fields.is_empty() {
+                pos = offset(src[pos..].strip_prefix(',')?);
+            }
+            if !src[pos..].starts_with(|c: char| c.is_ascii_lowercase() || c == '_') {
+                return None;
+            }
+            let name_len = src[pos..]
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(src.len() - pos);
+            let name = src[pos..pos + name_len].to_string();
+            pos = offset(src[pos + name_len..].strip_prefix(':')?);
+            let (field_type, len) = Type::parse(&src[pos..])?;
+            pos = offset(&src[pos + len..]);
+            match fields.binary_search_by(|(field, _)| field.cmp(&name)) {
+                Ok(_) => return None,
+                Err(i) => fields.insert(i, (name, field_type)),
+            }
+        }
+
+        Some((fields, pos + 1))
+    }
+
+    // reads the two comma separated types of a map, a pair or an or, including the closing bracket
+    fn parse_two(src: &str) -> Option<(Type, Type, usize)> {
+        let (first_type, first_len) = Type::parse(src)?;
+        let separator = src[first_len..].strip_prefix(',')?;
+        let spaces = separator.len() - separator.trim_start().len();
+        let (second_type, second_len) = Type::parse(separator.trim_start())?;
+        let len = first_len + 1 + spaces + second_len;
+        if !src[len..].starts_with('>') {
+            return None;
+        }
+
+        Some((first_type, second_type, len + 1))
+    }
+}
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Type::Int => write!(f, "int"),
+            Type::Bool => write!(f, "bool"),
+            Type::String => write!(f, "string"),
+            Type::Char => write!(f, "char"),
+            Type::Bytes => write!(f, "bytes"),
+            Type::Vector(el_type) => write!(f, "vector<{}>", el_type),
+            Type::Set(el_type) => write!(f, "set<{}>", el_type),
+            Type::Option(el_type) => write!(f, "option<{}>", el_type),
+            Type::Or(left_type, right_type) => write!(f, "or<{}, {}>", left_type, right_type),
+            Type::Map(key_type, value_type) => write!(f, "map<{}, {}>", key_type, value_type),
+            Type::Pair(first_type, second_type) => {
+                write!(f, "pair<{}, {}>", first_type, second_type)
+            }
+            Type::Record(fields) => {
+                let fields: Vec<String> = fields
+                    .iter()
+                    .map(|(name, field_type)| format!("{}: {}", name, field_type))
+                    .collect();
+                write!(f, "record<{}>", fields.join(", "))
+            }
+            Type::Quotation => write!(f, "quotation"),
+            Type::Any => write!(f, "any"),
+            Type::Unknown => write!(f, "unknown"),
+        }
+    }
+}